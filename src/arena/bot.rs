@@ -0,0 +1,146 @@
+use engine::command::Command;
+use engine::bitwise_engine::BitwiseGameState;
+use engine::bitboard::MapSize;
+use input;
+use strategy::Strategy;
+use strategy::config::{BotConfig, StrategyType};
+
+use time::{Duration, PreciseTime};
+
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::str::FromStr;
+
+const STATE_FILE: &str = "state.json";
+const COMMAND_FILE: &str = "command.txt";
+
+/**
+ * One of the players in an arena match.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Bot {
+    /// One of the strategies in this crate, configured the same way
+    /// as with `bot-config.json`.
+    Builtin {
+        name: String,
+        config: BotConfig
+    },
+    /// A bot executable that reads `state.json` and writes
+    /// `command.txt` in its working directory, like on the game
+    /// servers.
+    External(PathBuf)
+}
+
+impl FromStr for Bot {
+    type Err = Box<dyn Error>;
+
+    /**
+     * Bots can be given as the name of a strategy, a bot config file,
+     * or a bot executable.
+     */
+    fn from_str(s: &str) -> Result<Bot, Box<dyn Error>> {
+        let builtin = |strategy, static_opening| Ok(Bot::Builtin {
+            name: s.to_string(),
+            config: BotConfig { strategy, static_opening, ..BotConfig::default() }
+        });
+
+        match s {
+            "monte_carlo" => builtin(StrategyType::MonteCarlo, false),
+            "monte_carlo_tree" => builtin(StrategyType::MonteCarloTree, false),
+            "static_opening" => builtin(StrategyType::MonteCarlo, true),
+            "random" => builtin(StrategyType::Random, false),
+            path if path.ends_with(".json") => {
+                let config = input::json::read_bot_config_from_file(path)?;
+                let name = Path::new(path).file_stem().map(|n| n.to_string_lossy().into_owned()).unwrap_or_else(|| path.to_string());
+                Ok(Bot::Builtin { name, config })
+            },
+            path => {
+                let executable = fs::canonicalize(path)
+                    .map_err(|e| format!("'{}' is not a strategy, bot config or bot executable: {}", path, e))?;
+                Ok(Bot::External(executable))
+            }
+        }
+    }
+}
+
+impl fmt::Display for Bot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bot::Builtin { name, .. } => write!(f, "{}", name),
+            Bot::External(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl Bot {
+    /**
+     * Creates the strategy that plays one game for this bot.
+     * `working_dir` is only used by external bots, and needs to be
+     * different for each player in a match. `decision_folder` is only
+     * used by builtin bots configured to log their decisions.
+     */
+    pub fn strategy<M: MapSize>(&self, working_dir: PathBuf, decision_folder: &Path, seed: [u8; 16]) -> Box<dyn Strategy<M>> {
+        match self {
+            Bot::Builtin { config, .. } => config.build_strategy(seed, decision_folder),
+            Bot::External(executable) => Box::new(ExternalBot {
+                executable: executable.clone(),
+                working_dir
+            })
+        }
+    }
+}
+
+/**
+ * Runs a bot executable for each move. If it fails, it does nothing
+ * for that round, like on the game servers.
+ */
+pub struct ExternalBot {
+    pub executable: PathBuf,
+    pub working_dir: PathBuf
+}
+
+impl<M: MapSize> Strategy<M> for ExternalBot {
+    fn choose_move(&mut self, state: &BitwiseGameState<M>, _start_time: PreciseTime, _max_time: Duration) -> Command {
+        match self.run(state) {
+            Ok(command) => command,
+            Err(error) => {
+                println!("{} failed to make a move: {}", self.executable.display(), error);
+                Command::Nothing
+            }
+        }
+    }
+}
+
+impl ExternalBot {
+    fn run<M: MapSize>(&self, state: &BitwiseGameState<M>) -> Result<Command, Box<dyn Error>> {
+        fs::create_dir_all(&self.working_dir)?;
+        let state_path = self.working_dir.join(STATE_FILE);
+        let command_path = self.working_dir.join(COMMAND_FILE);
+        if command_path.exists() {
+            fs::remove_file(&command_path)?;
+        }
+
+        input::json::write_state_to_file(state, &state_path.to_string_lossy())?;
+
+        let status = process::Command::new(&self.executable)
+            .current_dir(&self.working_dir)
+            .stdout(process::Stdio::null())
+            .status()?;
+        if !status.success() {
+            return Err(format!("exited with {}", status).into());
+        }
+
+        input::command::read_own_command_from_file::<M>(&command_path.to_string_lossy())
+    }
+}
+
+/**
+ * A scratch directory for an external bot to play in.
+ */
+pub fn working_dir_for(player_type: char) -> PathBuf {
+    env::temp_dir().join(format!("zombot-arena-{}-{}", process::id(), player_type))
+}
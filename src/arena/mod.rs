@@ -0,0 +1,3 @@
+pub mod bot;
+pub mod referee;
+pub mod tournament;
@@ -0,0 +1,117 @@
+use engine::command::Command;
+use engine::status::GameStatus;
+use engine::bitwise_engine::{Player, BitwiseGameState};
+use engine::bitboard::MapSize;
+use engine::config::GameConfig;
+use arena::bot::{self, Bot};
+use replay::recorder::ReplayRecorder;
+
+use time::{Duration, PreciseTime};
+
+use std::error::Error;
+use std::fs;
+
+pub const STARTING_HEALTH: u8 = 100;
+pub const STARTING_ENERGY: u16 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MatchResult {
+    /// `Continue` if the round limit was reached without a winner.
+    pub status: GameStatus,
+    pub rounds: u16,
+    pub player_health: u8,
+    pub opponent_health: u8
+}
+
+/// Whether to print the board before every round of a match.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShowBoard {
+    Never,
+    Plain,
+    Coloured
+}
+
+pub fn initial_state<M: MapSize>(config: GameConfig) -> BitwiseGameState<M> {
+    let mut player = Player::empty();
+    player.health = STARTING_HEALTH;
+    player.energy = STARTING_ENERGY;
+    let opponent = player.clone();
+    BitwiseGameState::new(player, opponent, 0, config)
+}
+
+/**
+ * Plays a game between two bots from the given state, with the bitwise
+ * engine as the referee.
+ *
+ * Player B's bot is shown the state with the players swapped, so that
+ * it sees itself as player A like it would on the game servers. A bot
+ * that gives an invalid command does nothing for that round.
+ *
+ * The seed is only used by bots that make random moves. Player B's bot
+ * gets a different seed derived from it, so that a bot playing itself
+ * doesn't make the same random choices on both sides. If a recorder
+ * is given, the game is written out as a replay, along with player A's
+ * decision log if it keeps one.
+ */
+pub fn play_match<M: MapSize>(mut state: BitwiseGameState<M>, player_bot: &Bot, opponent_bot: &Bot, max_time: Duration, seed: [u8; 16], mut replay: Option<&mut ReplayRecorder>, show_board: ShowBoard) -> Result<MatchResult, Box<dyn Error>> {
+    let player_dir = bot::working_dir_for('A');
+    let opponent_dir = bot::working_dir_for('B');
+    let player_decisions = replay.as_ref().map(|r| r.folder().to_path_buf()).unwrap_or_else(|| player_dir.clone());
+    let mut player_strategy = player_bot.strategy::<M>(player_dir.clone(), &player_decisions, seed);
+    let mut opponent_strategy = opponent_bot.strategy::<M>(opponent_dir.clone(), &opponent_dir, opponent_seed(seed));
+
+    while state.status == GameStatus::Continue && state.round < state.config.max_rounds {
+        print_board(&state, show_board);
+        let player_command = player_strategy.choose_move(&state, PreciseTime::now(), max_time);
+
+        let opponent_command = opponent_strategy.choose_opponent_move(&state, PreciseTime::now(), max_time);
+
+        let player_command = checked_command('A', player_command, &state.player, &state.config);
+        let opponent_command = checked_command('B', opponent_command, &state.opponent, &state.config);
+        if let Some(ref mut recorder) = replay {
+            recorder.record_round(&state, player_command, opponent_command)?;
+        }
+        if show_board != ShowBoard::Never {
+            println!("A: {:?}, B: {:?}", player_command, opponent_command);
+        }
+        state.simulate(player_command, opponent_command);
+    }
+    print_board(&state, show_board);
+    if let Some(recorder) = replay {
+        recorder.record_final_state(&state)?;
+    }
+
+    let _ = fs::remove_dir_all(player_dir);
+    let _ = fs::remove_dir_all(opponent_dir);
+
+    Ok(MatchResult {
+        status: state.status,
+        rounds: state.round,
+        player_health: state.player.health,
+        opponent_health: state.opponent.health
+    })
+}
+
+fn opponent_seed(seed: [u8; 16]) -> [u8; 16] {
+    let mut opponent_seed = seed;
+    opponent_seed[0] ^= 1;
+    opponent_seed
+}
+
+fn print_board<M: MapSize>(state: &BitwiseGameState<M>, show_board: ShowBoard) {
+    match show_board {
+        ShowBoard::Never => {},
+        ShowBoard::Plain => println!("{}", state),
+        ShowBoard::Coloured => println!("{:#}", state)
+    }
+}
+
+fn checked_command<M: MapSize>(player_type: char, command: Command, player: &Player<M>, config: &GameConfig) -> Command {
+    match player.validate_command(command, config) {
+        Ok(()) => command,
+        Err(error) => {
+            println!("Player {} made an invalid move: {:?}, because {}", player_type, command, error);
+            Command::Nothing
+        }
+    }
+}
@@ -0,0 +1,257 @@
+use engine::status::GameStatus;
+use engine::bitwise_engine::BitwiseGameState;
+use engine::bitboard::MapSize;
+use engine::config::GameConfig;
+use arena::bot::Bot;
+use arena::referee;
+use replay::recorder::ReplayRecorder;
+use strategy;
+use strategy::monte_carlo::{self, RandomMoveOptions};
+
+use rand::{XorShiftRng, SeedableRng};
+use time::Duration;
+
+use std::error::Error;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Each pairing counts as if it had one extra drawn game, so that a
+/// bot that never wins still gets a finite rating.
+const PRIOR_DRAWS: f64 = 1.0;
+const ELO_ITERATIONS: usize = 1000;
+const Z_95: f64 = 1.96;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Score {
+    pub wins: u32,
+    pub draws: u32,
+    pub losses: u32
+}
+
+impl Score {
+    pub fn games(&self) -> u32 {
+        self.wins + self.draws + self.losses
+    }
+
+    /// Draws count as half a win.
+    pub fn points(&self) -> f64 {
+        f64::from(self.wins) + f64::from(self.draws) / 2.0
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        if self.games() == 0 {
+            0.5
+        } else {
+            self.points() / f64::from(self.games())
+        }
+    }
+
+    fn add(&mut self, other: Score) {
+        self.wins += other.wins;
+        self.draws += other.draws;
+        self.losses += other.losses;
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rating {
+    pub elo: f64,
+    /// Half the width of the 95% confidence interval.
+    pub error: f64
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TournamentResults {
+    pub names: Vec<String>,
+    /// `scores[i][j]` is bot i's record against bot j.
+    pub scores: Vec<Vec<Score>>
+}
+
+impl TournamentResults {
+    pub fn new(names: Vec<String>) -> TournamentResults {
+        let scores = vec!(vec!(Score::default(); names.len()); names.len());
+        TournamentResults { names, scores }
+    }
+
+    /**
+     * Records a game between bots i and j, where i was player A.
+     * Games that hit the round limit count as draws.
+     */
+    pub fn record(&mut self, i: usize, j: usize, status: GameStatus) {
+        match status {
+            GameStatus::PlayerWon => {
+                self.scores[i][j].wins += 1;
+                self.scores[j][i].losses += 1;
+            },
+            GameStatus::OpponentWon => {
+                self.scores[i][j].losses += 1;
+                self.scores[j][i].wins += 1;
+            },
+            GameStatus::Draw | GameStatus::Continue => {
+                self.scores[i][j].draws += 1;
+                self.scores[j][i].draws += 1;
+            }
+        }
+    }
+
+    pub fn total(&self, i: usize) -> Score {
+        let mut total = Score::default();
+        for &score in &self.scores[i] {
+            total.add(score);
+        }
+        total
+    }
+
+    /**
+     * Fits Elo ratings to all of the games at once with the
+     * Bradley-Terry model, using the MM algorithm from Hunter (2004).
+     * Ratings are relative to an average bot at 0.
+     *
+     * The confidence intervals come from the Fisher information of
+     * each bot's rating with the other ratings held fixed.
+     */
+    pub fn ratings(&self) -> Vec<Rating> {
+        let n = self.names.len();
+        let games = |i: usize, j: usize| f64::from(self.scores[i][j].games()) + if i == j { 0.0 } else { PRIOR_DRAWS };
+        let points = |i: usize, j: usize| self.scores[i][j].points() + if i == j { 0.0 } else { PRIOR_DRAWS / 2.0 };
+
+        let mut strength = vec!(1.0f64; n);
+        for _ in 0..ELO_ITERATIONS {
+            for i in 0..n {
+                let won: f64 = (0..n).filter(|&j| j != i).map(|j| points(i, j)).sum();
+                let expected: f64 = (0..n).filter(|&j| j != i).map(|j| games(i, j) / (strength[i] + strength[j])).sum();
+                if expected > 0.0 {
+                    strength[i] = won / expected;
+                }
+            }
+            let log_mean = strength.iter().map(|s| s.ln()).sum::<f64>() / n as f64;
+            for s in &mut strength {
+                *s /= log_mean.exp();
+            }
+        }
+
+        let elo_per_log_strength = 400.0 / 10f64.ln();
+        (0..n).map(|i| {
+            let information: f64 = (0..n).filter(|&j| j != i)
+                .map(|j| games(i, j) * strength[i] * strength[j] / (strength[i] + strength[j]).powi(2))
+                .sum();
+            Rating {
+                elo: strength[i].ln() * elo_per_log_strength,
+                error: Z_95 * elo_per_log_strength / information.sqrt()
+            }
+        }).collect()
+    }
+}
+
+impl fmt::Display for TournamentResults {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ratings = self.ratings();
+        let name_width = self.names.iter().map(|n| n.len()).max().unwrap_or(0).max(3);
+
+        let mut order: Vec<usize> = (0..self.names.len()).collect();
+        order.sort_by(|&a, &b| ratings[b].elo.partial_cmp(&ratings[a].elo).unwrap());
+
+        writeln!(f, "{:<w$}  {:>5}  {:>5}  {:>5}  {:>6}  {:>8}  {:>6}  {:>6}",
+                 "Bot", "Games", "Wins", "Draws", "Losses", "Win rate", "Elo", "95% CI", w = name_width)?;
+        for &i in &order {
+            let total = self.total(i);
+            writeln!(f, "{:<w$}  {:>5}  {:>5}  {:>5}  {:>6}  {:>7.1}%  {:>6.0}  {:>6}",
+                     self.names[i], total.games(), total.wins, total.draws, total.losses,
+                     total.win_rate() * 100.0, ratings[i].elo, format!("±{:.0}", ratings[i].error),
+                     w = name_width)?;
+        }
+
+        writeln!(f)?;
+        writeln!(f, "Win rate of each row against each column")?;
+        write!(f, "{:<w$}", "", w = name_width)?;
+        for i in 0..order.len() {
+            write!(f, "  {:>6}", format!("#{}", i+1))?;
+        }
+        writeln!(f)?;
+        for (row, &i) in order.iter().enumerate() {
+            write!(f, "{:<w$}", format!("#{} {}", row+1, self.names[i]), w = name_width + 3)?;
+            for &j in &order {
+                if i == j || self.scores[i][j].games() == 0 {
+                    write!(f, "  {:>6}", "-")?;
+                } else {
+                    write!(f, "  {:>5.1}%", self.scores[i][j].win_rate() * 100.0)?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+ * Turns a game number into an rng seed, so that the same tournament
+ * seed always plays the same games.
+ */
+pub fn seed_for_game(tournament_seed: u64, game: u32) -> [u8; 16] {
+    strategy::seed_from_u64(tournament_seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) ^ u64::from(game).wrapping_mul(0xc2b2_ae3d_27d4_eb4f))
+}
+
+/**
+ * Plays the first few rounds of a game with random moves, so that
+ * games between deterministic bots don't all play out the same way.
+ */
+pub fn random_opening<M: MapSize>(config: GameConfig, rounds: u16, seed: [u8; 16]) -> BitwiseGameState<M> {
+    let mut rng = XorShiftRng::from_seed(seed);
+    let random_moves = RandomMoveOptions::default();
+    let mut state = referee::initial_state::<M>(config);
+    while state.round < rounds && state.status == GameStatus::Continue {
+        let player_command = monte_carlo::random_move(&state.player, &state.opponent, &state.config, &random_moves, &mut rng);
+        let opponent_command = monte_carlo::random_move(&state.opponent, &state.player, &state.config, &random_moves, &mut rng);
+        state.simulate(player_command, opponent_command);
+    }
+    state
+}
+
+/**
+ * External bots are named by their path, so the separators are
+ * replaced to keep each replay in a single folder.
+ */
+fn replay_folder_name(player: &Bot, opponent: &Bot, game: u32) -> String {
+    format!("Game {:03} - {} vs {}", game, player, opponent)
+        .replace(['/', '\\'], "_")
+}
+
+pub struct TournamentOptions {
+    pub config: GameConfig,
+    /// The number of games for each pairing of bots, played once from
+    /// each side.
+    pub games: u32,
+    pub seed: u64,
+    pub random_opening_rounds: u16,
+    pub max_time: Duration,
+    /// If set, each game is recorded as a replay in its own folder
+    /// under this one.
+    pub replays: Option<PathBuf>
+}
+
+/**
+ * Plays every bot against every other bot. Each seeded game is played
+ * twice, with the bots swapping sides, so neither gets an advantage
+ * from the opening.
+ */
+pub fn play_tournament<M: MapSize, F: FnMut(&Bot, &Bot, referee::MatchResult)>(bots: &[Bot], options: &TournamentOptions, mut on_game: F) -> Result<TournamentResults, Box<dyn Error>> {
+    let mut results = TournamentResults::new(bots.iter().map(|b| b.to_string()).collect());
+    for i in 0..bots.len() {
+        for j in i+1..bots.len() {
+            for game in 0..options.games {
+                let seed = seed_for_game(options.seed, game);
+                let start = random_opening::<M>(options.config, options.random_opening_rounds, seed);
+                for &(a, b) in &[(i, j), (j, i)] {
+                    let mut recorder = match options.replays {
+                        Some(ref folder) => Some(ReplayRecorder::create(&folder.join(replay_folder_name(&bots[a], &bots[b], game)))?),
+                        None => None
+                    };
+                    let result = referee::play_match(start.clone(), &bots[a], &bots[b], options.max_time, seed, recorder.as_mut(), referee::ShowBoard::Never)?;
+                    results.record(a, b, result.status);
+                    on_game(&bots[a], &bots[b], result);
+                }
+            }
+        }
+    }
+    Ok(results)
+}
@@ -0,0 +1,128 @@
+extern crate zombot;
+extern crate time;
+use time::Duration;
+
+use zombot::*;
+use zombot::arena::bot::Bot;
+use zombot::arena::referee;
+use zombot::replay::recorder::ReplayRecorder;
+use zombot::engine::constants::*;
+use zombot::engine::config::GameConfig;
+use zombot::engine::status::GameStatus;
+use zombot::engine::bitboard::{MapSize, Map16x8, Map20x10};
+
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: arena [--state <state.json>] [--map <width>x<height>] [--time <millis>] [--replay <folder>] [--show] [--colour] <player A> <player B>
+
+Players are one of monte_carlo, monte_carlo_tree, static_opening,
+random, the path to a bot-config.json file, or the path to a bot
+executable that reads state.json and writes command.txt in its working
+directory.
+
+Without --state the match starts from a fresh game on a 16x8 map, or
+the size given by --map.
+
+With --replay, the match is recorded in the same layout as the replays
+from import-replay.
+
+--show prints the board before every round, and --colour draws it with
+terminal colours.";
+
+struct Options {
+    state_path: Option<String>,
+    map_size: (u8, u8),
+    max_time: Duration,
+    replay_path: Option<String>,
+    show_board: referee::ShowBoard,
+    player: Bot,
+    opponent: Bot
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut state_path = None;
+    let mut map_size = (Map16x8::FULL_MAP_WIDTH, Map16x8::MAP_HEIGHT);
+    let mut max_time = Duration::milliseconds(MAX_TIME_MILLIS);
+    let mut replay_path = None;
+    let mut show_board = referee::ShowBoard::Never;
+    let mut bots = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--state" => state_path = Some(args.next().ok_or("--state needs a file")?),
+            "--map" => {
+                let size = args.next().ok_or("--map needs a size")?;
+                let mut dimensions = size.split('x');
+                let width = dimensions.next().ok_or("Invalid map size")?.parse()?;
+                let height = dimensions.next().ok_or("Invalid map size")?.parse()?;
+                map_size = (width, height);
+            },
+            "--time" => max_time = Duration::milliseconds(args.next().ok_or("--time needs a number of milliseconds")?.parse()?),
+            "--replay" => replay_path = Some(args.next().ok_or("--replay needs a folder")?),
+            "--show" => if show_board == referee::ShowBoard::Never { show_board = referee::ShowBoard::Plain },
+            "--colour" => show_board = referee::ShowBoard::Coloured,
+            "--help" => return Err(USAGE.into()),
+            bot => bots.push(bot.parse::<Bot>()?)
+        }
+    }
+
+    if bots.len() != 2 {
+        return Err(USAGE.into());
+    }
+    let opponent = bots.pop().unwrap();
+    let player = bots.pop().unwrap();
+
+    if let Some(ref path) = state_path {
+        map_size = input::json::read_map_size_from_file(path)?;
+    }
+
+    Ok(Options { state_path, map_size, max_time, replay_path, show_board, player, opponent })
+}
+
+fn run<M: MapSize>(options: &Options) -> Result<referee::MatchResult, Box<dyn Error>> {
+    let state = match options.state_path {
+        Some(ref path) => input::json::read_state_from_file::<M>(path)?,
+        None => referee::initial_state::<M>(GameConfig::default())
+    };
+    let mut recorder = match options.replay_path {
+        Some(ref path) => Some(ReplayRecorder::create(Path::new(path))?),
+        None => None
+    };
+    referee::play_match(state, &options.player, &options.opponent, options.max_time, INIT_SEED, recorder.as_mut(), options.show_board)
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    let result = match options.map_size {
+        (width, height) if width == Map16x8::FULL_MAP_WIDTH && height == Map16x8::MAP_HEIGHT => run::<Map16x8>(&options),
+        (width, height) if width == Map20x10::FULL_MAP_WIDTH && height == Map20x10::MAP_HEIGHT => run::<Map20x10>(&options),
+        (width, height) => Err(format!("Unsupported map size: {}x{}", width, height).into())
+    };
+    let result = match result {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("Error while playing the match: {}", error);
+            process::exit(1);
+        }
+    };
+
+    match result.status {
+        GameStatus::PlayerWon => println!("Winner: A ({})", options.player),
+        GameStatus::OpponentWon => println!("Winner: B ({})", options.opponent),
+        GameStatus::Draw => println!("Draw"),
+        GameStatus::Continue => println!("No winner after the round limit"),
+    }
+    println!("Health: A {}, B {}", result.player_health, result.opponent_health);
+    println!("Rounds: {}", result.rounds);
+}
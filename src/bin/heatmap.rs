@@ -0,0 +1,114 @@
+extern crate zombot;
+
+use zombot::input;
+use zombot::strategy::heatmap;
+use zombot::replay::recorder::{self, DECISION_FILE};
+use zombot::engine::bitboard::{MapSize, Map16x8, Map20x10};
+
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "Usage: heatmap [--map <width>x<height>] [--ppm] <decision folder> <output folder>
+
+Reads the decision logs written by a bot with decisionLog turned on,
+and draws where it thought each type of building should go.
+
+The scores for the whole game are written to heatmap.csv, and each
+round is drawn as Round NNN.svg, or Round NNN.ppm with --ppm. Winning
+moves are red, losing moves are blue, and moves that weren't
+considered are grey.
+
+The map is 16x8 unless --map gives another size.";
+
+struct Options {
+    decision_folder: PathBuf,
+    output_folder: PathBuf,
+    map_size: (u8, u8),
+    ppm: bool
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut map_size = (Map16x8::FULL_MAP_WIDTH, Map16x8::MAP_HEIGHT);
+    let mut ppm = false;
+    let mut folders = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--map" => {
+                let size = args.next().ok_or("--map needs a size")?;
+                let mut dimensions = size.split('x');
+                let width = dimensions.next().ok_or("Invalid map size")?.parse()?;
+                let height = dimensions.next().ok_or("Invalid map size")?.parse()?;
+                map_size = (width, height);
+            },
+            "--ppm" => ppm = true,
+            "--help" => return Err(USAGE.into()),
+            folder => folders.push(PathBuf::from(folder))
+        }
+    }
+
+    if folders.len() != 2 {
+        return Err(USAGE.into());
+    }
+    let output_folder = folders.pop().unwrap();
+    let decision_folder = folders.pop().unwrap();
+    Ok(Options { decision_folder, output_folder, map_size, ppm })
+}
+
+fn run<M: MapSize>(options: &Options) -> Result<u16, Box<dyn Error>> {
+    fs::create_dir_all(&options.output_folder)?;
+    let mut csv = File::create(options.output_folder.join("heatmap.csv"))?;
+    writeln!(csv, "{}", heatmap::CSV_HEADER)?;
+
+    let mut rounds = 0;
+    for round in 0..=u16::MAX {
+        let round_folder = recorder::round_folder(&options.decision_folder, round);
+        if !round_folder.exists() {
+            break;
+        }
+        let decision_path = round_folder.join(DECISION_FILE);
+        if !decision_path.exists() {
+            continue;
+        }
+
+        let record = input::json::read_decision_record_from_file(&decision_path.to_string_lossy())
+            .map_err(|e| format!("Could not read {}: {}", decision_path.display(), e))?;
+        let heatmaps = heatmap::decision_heatmaps::<M>(&record)?;
+        write!(csv, "{}", heatmap::csv_rows(round, &heatmaps))?;
+
+        let image_name = format!("Round {:03}.{}", round, if options.ppm { "ppm" } else { "svg" });
+        let image = if options.ppm { heatmap::to_ppm(&heatmaps) } else { heatmap::to_svg(&heatmaps).into_bytes() };
+        fs::write(options.output_folder.join(image_name), image)?;
+        rounds += 1;
+    }
+    Ok(rounds)
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    let result = match options.map_size {
+        (width, height) if width == Map16x8::FULL_MAP_WIDTH && height == Map16x8::MAP_HEIGHT => run::<Map16x8>(&options),
+        (width, height) if width == Map20x10::FULL_MAP_WIDTH && height == Map20x10::MAP_HEIGHT => run::<Map20x10>(&options),
+        (width, height) => Err(format!("Unsupported map size: {}x{}", width, height).into())
+    };
+
+    match result {
+        Ok(rounds) => println!("Drew heatmaps for {} rounds", rounds),
+        Err(error) => {
+            println!("Error while drawing heatmaps: {}", error);
+            process::exit(1);
+        }
+    }
+}
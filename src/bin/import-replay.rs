@@ -0,0 +1,40 @@
+extern crate zombot;
+
+use zombot::replay::import;
+
+use std::env;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: import-replay <replay folder> <output folder>
+
+Converts a replay from the game server, with a folder for each player
+in every round, into the Round NNN/state.json, PlayerCommand.txt and
+OpponentCommand.txt layout used by the tests.
+
+Every round is then simulated, and rounds where the engine doesn't
+reproduce the next round's state are reported.";
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 2 || args[0] == "--help" {
+        println!("{}", USAGE);
+        process::exit(1);
+    }
+
+    let report = match import::import_replay(Path::new(&args[0]), Path::new(&args[1])) {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("Error while importing the replay: {}", error);
+            process::exit(1);
+        }
+    };
+
+    println!("Imported {} rounds", report.rounds);
+    if !report.mismatched_rounds.is_empty() {
+        for round in &report.mismatched_rounds {
+            println!("Simulating round {} did not give the state in round {}", round, round + 1);
+        }
+        process::exit(2);
+    }
+}
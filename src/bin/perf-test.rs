@@ -4,12 +4,26 @@ use time::{PreciseTime, Duration};
 
 use zombot::*;
 use zombot::engine::constants::*;
+use zombot::strategy::config::BotConfig;
 
 const STATE_PATH: &str = "tests/state0.json";
 
+use std::env;
+use std::path::Path;
 use std::process;
 
 fn main() {
+    let bot_config = match env::args().nth(1) {
+        Some(path) => match input::json::read_bot_config_from_file(&path) {
+            Ok(ok) => ok,
+            Err(error) => {
+                println!("Error while parsing bot config: {}", error);
+                process::exit(1);
+            }
+        },
+        None => BotConfig { static_opening: false, ..BotConfig::default() }
+    };
+
     println!("Running bitwise engine");
     let start_time = PreciseTime::now();
     let state = match input::json::read_bitwise_state_from_file(STATE_PATH) {
@@ -21,6 +35,5 @@ fn main() {
     };
     let max_time = Duration::milliseconds(MAX_TIME_MILLIS);
 
-    #[cfg(feature = "full-monte-carlo-tree")] strategy::monte_carlo_tree::choose_move(&state, start_time, max_time);
-    #[cfg(not(feature = "full-monte-carlo-tree"))] strategy::monte_carlo::choose_move(&state, start_time, max_time);
+    bot_config.build_strategy(INIT_SEED, Path::new("decisions")).choose_move(&state, start_time, max_time);
 }
@@ -0,0 +1,52 @@
+extern crate zombot;
+
+use zombot::input;
+use zombot::replay::divergence;
+use zombot::replay::recorder::{self, STATE_FILE};
+use zombot::engine::bitboard::{MapSize, Map16x8, Map20x10};
+
+use std::env;
+use std::error::Error;
+use std::path::Path;
+use std::process;
+
+const USAGE: &str = "Usage: replay-diff <replay folder>
+
+Simulates every round of a replay in the Round NNN/state.json layout,
+as written by import-replay or recorded by the arena, and prints what
+differs wherever the engine doesn't reproduce the next round's state.";
+
+fn run<M: MapSize>(replay_folder: &Path) -> Result<usize, Box<dyn Error>> {
+    let report = divergence::find_divergences::<M>(replay_folder)?;
+    for divergence in &report.divergences {
+        println!("{}", divergence);
+    }
+    println!("{} of {} rounds diverged", report.divergences.len(), report.rounds);
+    Ok(report.divergences.len())
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() != 1 || args[0] == "--help" {
+        println!("{}", USAGE);
+        process::exit(1);
+    }
+    let replay_folder = Path::new(&args[0]);
+
+    let first_state = recorder::round_folder(replay_folder, 0).join(STATE_FILE);
+    let result = match input::json::read_map_size_from_file(&first_state.to_string_lossy()) {
+        Ok((width, height)) if width == Map16x8::FULL_MAP_WIDTH && height == Map16x8::MAP_HEIGHT => run::<Map16x8>(replay_folder),
+        Ok((width, height)) if width == Map20x10::FULL_MAP_WIDTH && height == Map20x10::MAP_HEIGHT => run::<Map20x10>(replay_folder),
+        Ok((width, height)) => Err(format!("Unsupported map size: {}x{}", width, height).into()),
+        Err(error) => Err(error)
+    };
+
+    match result {
+        Ok(0) => {},
+        Ok(_) => process::exit(2),
+        Err(error) => {
+            println!("Error while reading the replay: {}", error);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+extern crate zombot;
+
+use zombot::input;
+use zombot::replay::viewer::Replay;
+use zombot::replay::recorder::{self, STATE_FILE};
+use zombot::engine::bitboard::{MapSize, Map16x8, Map20x10};
+
+use std::env;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "Usage: replay-viewer [--decisions <folder>] [--colour] <replay folder>
+
+Steps through a replay in the Round NNN/state.json layout, as written
+by import-replay or recorded by the arena. Each round shows both
+commands, and the engine's prediction of the next round next to the
+recorded one.
+
+If the bot's decision logs are in the replay folder, or the folder
+given with --decisions, its best scoring moves are shown too.
+
+Commands: enter or n for the next round, p for the previous one,
+g <round> to go to a round, and q to quit.";
+
+const CLEAR_SCREEN: &str = "\x1b[2J\x1b[H";
+
+struct Options {
+    replay_folder: PathBuf,
+    decision_folder: Option<PathBuf>,
+    colour: bool
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut replay_folder = None;
+    let mut decision_folder = None;
+    let mut colour = false;
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--decisions" => decision_folder = Some(PathBuf::from(args.next().ok_or("--decisions needs a folder")?)),
+            "--colour" => colour = true,
+            "--help" => return Err(USAGE.into()),
+            folder if replay_folder.is_none() => replay_folder = Some(PathBuf::from(folder)),
+            _ => return Err(USAGE.into())
+        }
+    }
+
+    let replay_folder = replay_folder.ok_or(USAGE)?;
+    Ok(Options { replay_folder, decision_folder, colour })
+}
+
+fn run<M: MapSize>(options: &Options) -> Result<(), Box<dyn Error>> {
+    let decision_folder = options.decision_folder.as_ref().unwrap_or(&options.replay_folder);
+    let replay = Replay::<M>::load(&options.replay_folder, decision_folder)?;
+
+    let stdin = io::stdin();
+    let mut round = 0;
+    loop {
+        print!("{}{}", CLEAR_SCREEN, replay.render_round(round, options.colour));
+        print!("\nRound {} of {}: [n]ext, [p]revious, [g]o <round>, [q]uit > ", round, replay.last_round());
+        io::stdout().flush()?;
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None | Some("n") => round = (round + 1).min(replay.last_round()),
+            Some("p") => round = round.saturating_sub(1),
+            Some("g") => if let Some(target) = words.next().and_then(|w| w.parse().ok()) {
+                round = u16::min(target, replay.last_round());
+            },
+            Some("q") => return Ok(()),
+            Some(_) => {}
+        }
+    }
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    let first_state = recorder::round_folder(&options.replay_folder, 0).join(STATE_FILE);
+    let result = match input::json::read_map_size_from_file(&first_state.to_string_lossy()) {
+        Ok((width, height)) if width == Map16x8::FULL_MAP_WIDTH && height == Map16x8::MAP_HEIGHT => run::<Map16x8>(&options),
+        Ok((width, height)) if width == Map20x10::FULL_MAP_WIDTH && height == Map20x10::MAP_HEIGHT => run::<Map20x10>(&options),
+        Ok((width, height)) => Err(format!("Unsupported map size: {}x{}", width, height).into()),
+        Err(error) => Err(error)
+    };
+
+    if let Err(error) = result {
+        println!("Error while viewing the replay {}: {}", Path::new(&options.replay_folder).display(), error);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,108 @@
+extern crate zombot;
+extern crate time;
+use time::Duration;
+
+use zombot::arena::bot::Bot;
+use zombot::arena::tournament::{self, TournamentOptions};
+use zombot::engine::constants::*;
+use zombot::engine::config::GameConfig;
+use zombot::engine::status::GameStatus;
+use zombot::engine::bitboard::Map16x8;
+
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::prelude::*;
+use std::path::PathBuf;
+use std::process;
+
+const USAGE: &str = "Usage: tournament [--games <n>] [--seed <n>] [--opening <rounds>] [--time <millis>] [--output <file>] [--replays <folder>] <bot> <bot> [<bot>...]
+
+Plays every bot against every other bot, n games from each side, and
+prints the win rates and Elo ratings.
+
+Bots are one of monte_carlo, monte_carlo_tree, static_opening, random,
+the path to a bot-config.json file, or the path to a bot executable.
+Strategy variants can be compared by writing a config file for each.
+
+Each game starts with the given number of random rounds, chosen by the
+seed, so that deterministic bots don't play the same game every time.
+
+With --replays, every game is recorded in its own folder, in the same
+layout as the replays from import-replay.";
+
+struct Options {
+    tournament: TournamentOptions,
+    output: Option<String>,
+    bots: Vec<Bot>
+}
+
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut tournament = TournamentOptions {
+        config: GameConfig::default(),
+        games: 10,
+        seed: 0,
+        random_opening_rounds: 4,
+        max_time: Duration::milliseconds(MAX_TIME_MILLIS),
+        replays: None
+    };
+    let mut output = None;
+    let mut bots = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--games" => tournament.games = args.next().ok_or("--games needs a number")?.parse()?,
+            "--seed" => tournament.seed = args.next().ok_or("--seed needs a number")?.parse()?,
+            "--opening" => tournament.random_opening_rounds = args.next().ok_or("--opening needs a number of rounds")?.parse()?,
+            "--time" => tournament.max_time = Duration::milliseconds(args.next().ok_or("--time needs a number of milliseconds")?.parse()?),
+            "--output" => output = Some(args.next().ok_or("--output needs a file")?),
+            "--replays" => tournament.replays = Some(PathBuf::from(args.next().ok_or("--replays needs a folder")?)),
+            "--help" => return Err(USAGE.into()),
+            bot => bots.push(bot.parse::<Bot>()?)
+        }
+    }
+
+    if bots.len() < 2 {
+        return Err(USAGE.into());
+    }
+
+    Ok(Options { tournament, output, bots })
+}
+
+fn main() {
+    let options = match parse_args() {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("{}", error);
+            process::exit(1);
+        }
+    };
+
+    let results = tournament::play_tournament::<Map16x8, _>(&options.bots, &options.tournament, |player, opponent, result| {
+        let winner = match result.status {
+            GameStatus::PlayerWon => format!("{} won", player),
+            GameStatus::OpponentWon => format!("{} won", opponent),
+            GameStatus::Draw | GameStatus::Continue => "draw".to_string()
+        };
+        println!("{} vs {}: {} after {} rounds", player, opponent, winner, result.rounds);
+    });
+    let results = match results {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("Error while playing the tournament: {}", error);
+            process::exit(1);
+        }
+    };
+
+    println!();
+    print!("{}", results);
+
+    if let Some(path) = options.output {
+        let written = File::create(&path).and_then(|mut file| write!(file, "{}", results));
+        if let Err(error) = written {
+            println!("Error while writing results file: {}", error);
+            process::exit(1);
+        }
+    }
+}
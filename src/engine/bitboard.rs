@@ -0,0 +1,165 @@
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, Not, Shl, ShlAssign, Shr};
+
+/**
+ * The integer type used to store one bit per cell on one player's
+ * half of the map.
+ */
+pub trait Bitboard: Debug + Clone + Copy + PartialEq + Eq + Hash + Send + Sync
+    + BitAnd<Output = Self> + BitOr<Output = Self> + BitXor<Output = Self> + Not<Output = Self>
+    + Shl<u32, Output = Self> + Shr<u32, Output = Self>
+    + BitAndAssign + BitOrAssign + ShlAssign<u32>
+{
+    const ZERO: Self;
+
+    fn bit(index: u8) -> Self;
+    fn count_ones(self) -> u32;
+    fn trailing_zeros(self) -> u32;
+
+    /**
+     * The index of the set bit with the given rank, counting from the
+     * least significant bit and starting at 0.
+     */
+    fn select(self, rank: u32) -> u8;
+}
+
+impl Bitboard for u64 {
+    const ZERO: u64 = 0;
+
+    fn bit(index: u8) -> u64 {
+        1u64 << index
+    }
+
+    fn count_ones(self) -> u32 {
+        u64::count_ones(self)
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        u64::trailing_zeros(self)
+    }
+
+    fn select(self, rank: u32) -> u8 {
+        // Adapted from https://graphics.stanford.edu/~seander/bithacks.html#SelectPosFromMSBRank
+        let v = self;
+
+        let mut r = u64::from(v.count_ones()) - u64::from(rank);
+
+        let a: u64 =  v - ((v >> 1) & (!0u64/3));
+        let b: u64 = (a & (!0u64/5)) + ((a >> 2) & (!0u64/5));
+        let c: u64 = (b + (b >> 4)) & (!0u64/0x11);
+        let d: u64 = (c + (c >> 8)) & (!0u64/0x101);
+        let mut t: u64 = (d >> 32) + (d >> 48);
+
+        let mut s: u64 = 64;
+        s -= (t.wrapping_sub(r) & 256) >> 3; r -= t & (t.wrapping_sub(r) >> 8);
+        t  = (d >> (s - 16)) & 0xff;
+        s -= (t.wrapping_sub(r) & 256) >> 4; r -= t & (t.wrapping_sub(r) >> 8);
+        t  = (c >> (s - 8)) & 0xf;
+        s -= (t.wrapping_sub(r) & 256) >> 5; r -= t & (t.wrapping_sub(r) >> 8);
+        t  = (b >> (s - 4)) & 0x7;
+        s -= (t.wrapping_sub(r) & 256) >> 6; r -= t & (t.wrapping_sub(r) >> 8);
+        t  = (a >> (s - 2)) & 0x3;
+        s -= (t.wrapping_sub(r) & 256) >> 7; r -= t & (t.wrapping_sub(r) >> 8);
+        t  = (v >> (s - 1)) & 0x1;
+        s -= (t.wrapping_sub(r) & 256) >> 8;
+        s = 65 - s;
+
+        64 - s as u8
+    }
+}
+
+impl Bitboard for u128 {
+    const ZERO: u128 = 0;
+
+    fn bit(index: u8) -> u128 {
+        1u128 << index
+    }
+
+    fn count_ones(self) -> u32 {
+        u128::count_ones(self)
+    }
+
+    fn trailing_zeros(self) -> u32 {
+        u128::trailing_zeros(self)
+    }
+
+    fn select(self, rank: u32) -> u8 {
+        let low = self as u64;
+        let low_count = low.count_ones();
+        if rank < low_count {
+            low.select(rank)
+        } else {
+            64 + ((self >> 64) as u64).select(rank - low_count)
+        }
+    }
+}
+
+/**
+ * The dimensions of a map, and the bitboard type that's big enough
+ * to hold one player's half of it.
+ *
+ * Cells are numbered row by row from the top left of a player's
+ * half, with x mirrored for the player on the right so that bit 0 is
+ * always in the player's back column.
+ */
+pub trait MapSize: Debug + Clone + Copy + PartialEq + Eq + Send + Sync + 'static {
+    type Bits: Bitboard;
+    /// One pair of bitfields for each missile that can share a cell,
+    /// which is `SINGLE_MAP_WIDTH / MISSILE_SPEED`.
+    type Missiles: Debug + Clone + Copy + PartialEq + Eq + Default + Send + Sync
+        + AsRef<[(Self::Bits, Self::Bits)]> + AsMut<[(Self::Bits, Self::Bits)]>;
+
+    const FULL_MAP_WIDTH: u8;
+    const SINGLE_MAP_WIDTH: u8 = Self::FULL_MAP_WIDTH / 2;
+    const MAP_HEIGHT: u8;
+    const NUMBER_OF_MAP_POSITIONS: usize = Self::SINGLE_MAP_WIDTH as usize * Self::MAP_HEIGHT as usize;
+
+    const FIRST_ROW_MASK: Self::Bits;
+    const LEFT_COL_MASK: Self::Bits;
+    const RIGHT_COL_MASK: Self::Bits;
+    const ALL_CELLS_MASK: Self::Bits;
+
+    fn row_mask(y: u8) -> Self::Bits {
+        Self::FIRST_ROW_MASK << (u32::from(y) * u32::from(Self::SINGLE_MAP_WIDTH))
+    }
+}
+
+/// The largest number of cells on one side of any supported map.
+pub const MAX_MAP_POSITIONS: usize = 128;
+/// The largest height of any supported map.
+pub const MAX_MAP_HEIGHT: usize = 16;
+/// The most missiles that can share a cell on any supported map.
+pub const MAX_MISSILE_TIERS: usize = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Map16x8;
+
+impl MapSize for Map16x8 {
+    type Bits = u64;
+    type Missiles = [(u64, u64); 4];
+
+    const FULL_MAP_WIDTH: u8 = 16;
+    const MAP_HEIGHT: u8 = 8;
+
+    const FIRST_ROW_MASK: u64 = 0x0000_0000_0000_00ff;
+    const LEFT_COL_MASK: u64 = 0x0101_0101_0101_0101;
+    const RIGHT_COL_MASK: u64 = 0x8080_8080_8080_8080;
+    const ALL_CELLS_MASK: u64 = 0xffff_ffff_ffff_ffff;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Map20x10;
+
+impl MapSize for Map20x10 {
+    type Bits = u128;
+    type Missiles = [(u128, u128); 5];
+
+    const FULL_MAP_WIDTH: u8 = 20;
+    const MAP_HEIGHT: u8 = 10;
+
+    const FIRST_ROW_MASK: u128 = 0x0000_0000_0000_0000_0000_0000_0000_03ff;
+    const LEFT_COL_MASK: u128 = 0x0000_0000_0401_0040_1004_0100_4010_0401;
+    const RIGHT_COL_MASK: u128 = 0x0000_0008_0200_8020_0802_0080_2008_0200;
+    const ALL_CELLS_MASK: u128 = 0x0000_000f_ffff_ffff_ffff_ffff_ffff_ffff;
+}
@@ -1,46 +1,56 @@
-use engine::command::{Command, BuildingType};
+use engine::command::{Command, CommandError, BuildingType};
 use engine::geometry::Point;
 use engine::constants::*;
+use engine::config::GameConfig;
+use engine::bitboard::{Bitboard, MapSize, Map16x8};
 use engine::status::GameStatus;
+use engine::zobrist;
+use engine::legal_commands::{LegalCommands, ENERGY_PRODUCTION_CUTOFF, ENERGY_STORAGE_CUTOFF};
 
-use arrayvec::ArrayVec;
+use arrayvec::{ArrayVec, CapacityError};
 
-const LEFT_COL_MASK: u64 = 0x0101_0101_0101_0101;
-const RIGHT_COL_MASK: u64 = 0x8080_8080_8080_8080;
+use std::mem;
 
-const ROW_MASKS: [u64; MAP_HEIGHT as usize] = [
-    0x0000_0000_0000_00ff,
-    0x0000_0000_0000_ff00,
-    0x0000_0000_00ff_0000,
-    0x0000_0000_ff00_0000,
-    0x0000_00ff_0000_0000,
-    0x0000_ff00_0000_0000,
-    0x00ff_0000_0000_0000,
-    0xff00_0000_0000_0000,
-];
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct BitwiseGameState {
+#[derive(Debug, Clone)]
+pub struct BitwiseGameState<M: MapSize = Map16x8> {
     pub status: GameStatus,
-    pub player: Player,
-    pub opponent: Player,
-    pub round: u16
+    pub player: Player<M>,
+    pub opponent: Player<M>,
+    pub round: u16,
+    pub config: GameConfig,
+    hash: u64
 }
 
+/**
+ * The hash is left out, since it's worked out from the rest of the
+ * state.
+ */
+impl<M: MapSize> PartialEq for BitwiseGameState<M> {
+    fn eq(&self, other: &BitwiseGameState<M>) -> bool {
+        self.status == other.status
+            && self.player == other.player
+            && self.opponent == other.opponent
+            && self.round == other.round
+            && self.config == other.config
+    }
+}
+
+impl<M: MapSize> Eq for BitwiseGameState<M> {}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Player {
+pub struct Player<M: MapSize = Map16x8> {
     pub energy: u16,
     pub health: u8,
     pub unconstructed: ArrayVec<[UnconstructedBuilding; MAX_CONCURRENT_CONSTRUCTION]>,
-    pub buildings: [u64; DEFENCE_HEALTH],
-    pub occupied: u64,
+    pub buildings: [M::Bits; DEFENCE_HEALTH],
+    pub occupied: M::Bits,
     
-    pub energy_towers: u64,
+    pub energy_towers: M::Bits,
 
-    pub missile_towers: [u64; MISSILE_COOLDOWN_STATES],
+    pub missile_towers: [M::Bits; MISSILE_COOLDOWN_STATES],
     pub firing_tower: usize,
     
-    pub missiles: [(u64, u64); MISSILE_MAX_SINGLE_CELL],
+    pub missiles: M::Missiles,
     pub tesla_cooldowns: ArrayVec<[TeslaCooldown; TESLA_MAX]>,
 
     pub iron_curtain_available: bool,
@@ -61,11 +71,42 @@ pub struct TeslaCooldown {
     pub age: u16
 }
 
+/**
+ * What `simulate_with_undo` needs to put a state back the way it was
+ * before a round. Only the parts of each player that a round can
+ * change are kept, so the config isn't copied along with them.
+ */
+#[derive(Debug, Clone)]
+pub struct Undo<M: MapSize = Map16x8> {
+    status: GameStatus,
+    hash: u64,
+    player: PlayerUndo<M>,
+    opponent: PlayerUndo<M>
+}
 
-impl BitwiseGameState {
+#[derive(Debug, Clone)]
+struct PlayerUndo<M: MapSize> {
+    energy: u16,
+    health: u8,
+    unconstructed: ArrayVec<[UnconstructedBuilding; MAX_CONCURRENT_CONSTRUCTION]>,
+    buildings: [M::Bits; DEFENCE_HEALTH],
+    occupied: M::Bits,
+    energy_towers: M::Bits,
+    missile_towers: [M::Bits; MISSILE_COOLDOWN_STATES],
+    firing_tower: usize,
+    missiles: M::Missiles,
+    tesla_cooldowns: ArrayVec<[TeslaCooldown; TESLA_MAX]>,
+    iron_curtain_available: bool,
+    iron_curtain_remaining: u8
+}
+
+
+impl<M: MapSize> BitwiseGameState<M> {
     pub fn simulate(&mut self, player_command: Command, opponent_command: Command) -> GameStatus {
-        self.player.perform_command(player_command);
-        self.opponent.perform_command(opponent_command);
+        let config = &self.config;
+
+        self.player.perform_command(player_command, config);
+        self.opponent.perform_command(opponent_command, config);
 
         self.player.update_construction();
         self.opponent.update_construction();
@@ -73,88 +114,199 @@ impl BitwiseGameState {
         self.player.add_missiles();
         self.opponent.add_missiles();
 
-        BitwiseGameState::fire_teslas(&mut self.player, &mut self.opponent);
+        BitwiseGameState::fire_teslas(&mut self.player, &mut self.opponent, config);
 
-        BitwiseGameState::move_and_collide_missiles(&mut self.player, &mut self.opponent.missiles);
-        BitwiseGameState::move_and_collide_missiles(&mut self.opponent, &mut self.player.missiles);
+        BitwiseGameState::move_and_collide_missiles(&mut self.player, &mut self.opponent.missiles, config);
+        BitwiseGameState::move_and_collide_missiles(&mut self.opponent, &mut self.player.missiles, config);
 
-        BitwiseGameState::add_energy(&mut self.player);
-        BitwiseGameState::add_energy(&mut self.opponent);
+        BitwiseGameState::add_energy(&mut self.player, config);
+        BitwiseGameState::add_energy(&mut self.opponent, config);
 
-        BitwiseGameState::update_iron_curtain(&mut self.player, self.round);
-        BitwiseGameState::update_iron_curtain(&mut self.opponent, self.round);
+        BitwiseGameState::update_iron_curtain(&mut self.player, self.round, config);
+        BitwiseGameState::update_iron_curtain(&mut self.opponent, self.round, config);
 
         self.round += 1;
 
         self.update_status();
         self.status
     }
+
+    /**
+     * Simulates a round like `simulate`, but checks the commands
+     * first. An invalid command does nothing, the same as in the game
+     * engine, and the reason it was invalid is returned for each
+     * player. This is for commands from outside the bot, like the ones
+     * in a replay. The bot's own commands are always valid.
+     */
+    pub fn try_simulate(&mut self, player_command: Command, opponent_command: Command) -> (GameStatus, Result<(), CommandError>, Result<(), CommandError>) {
+        let player_result = self.player.validate_command(player_command, &self.config);
+        let opponent_result = self.opponent.validate_command(opponent_command, &self.config);
+        let status = self.simulate(
+            if player_result.is_ok() { player_command } else { Command::Nothing },
+            if opponent_result.is_ok() { opponent_command } else { Command::Nothing }
+        );
+        (status, player_result, opponent_result)
+    }
+
+    /**
+     * Simulates a round the same as `simulate`, and also returns what
+     * `unsimulate` needs to go back to the state before it. This lets
+     * a search walk down the game tree and back up again on one state,
+     * instead of cloning it at every step. The state's hash is updated
+     * along with it.
+     */
+    pub fn simulate_with_undo(&mut self, player_command: Command, opponent_command: Command) -> (GameStatus, Undo<M>) {
+        let undo = Undo {
+            status: self.status,
+            hash: self.hash,
+            player: PlayerUndo::record(&self.player),
+            opponent: PlayerUndo::record(&self.opponent)
+        };
+        let status = self.simulate(player_command, opponent_command);
+        self.hash ^= self.hash_change(&undo);
+        (status, undo)
+    }
+
+    /**
+     * Restores the state from before the round that returned `undo`.
+     * Rounds have to be undone in the reverse of the order they were
+     * simulated.
+     */
+    pub fn unsimulate(&mut self, undo: Undo<M>) {
+        self.round -= 1;
+        self.status = undo.status;
+        self.hash = undo.hash;
+        undo.player.restore(&mut self.player);
+        undo.opponent.restore(&mut self.opponent);
+    }
+
+    /**
+     * A Zobrist hash of everything in the state that affects play.
+     * Each building, missile, energy level and so on has its own
+     * random key, and the hash is all of them XORed together, so
+     * states that are equal once sorted have the same hash.
+     *
+     * This is kept up to date by `simulate_with_undo` and
+     * `unsimulate`. `simulate` leaves it alone, since it's what the
+     * random rollouts use and they never look at the hash, so after
+     * `simulate` or changing the state by hand, call `rehash` first.
+     */
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Works the hash out again from the whole state.
+    pub fn rehash(&mut self) {
+        self.hash = self.zobrist_hash();
+    }
+
+    /// The hash of the state as it is now, worked out from scratch.
+    pub fn zobrist_hash(&self) -> u64 {
+        zobrist::round(self.round)
+            ^ self.player.zobrist_hash(zobrist::PLAYER)
+            ^ self.opponent.zobrist_hash(zobrist::OPPONENT)
+    }
+
+    /**
+     * How the hash changed in the round that `undo` was recorded for.
+     * Only the parts of the state that changed in the round are
+     * hashed, and for the board only the cells that changed.
+     */
+    fn hash_change(&self, undo: &Undo<M>) -> u64 {
+        zobrist::round(self.round - 1) ^ zobrist::round(self.round)
+            ^ undo.player.hash_change(zobrist::PLAYER, &self.player)
+            ^ undo.opponent.hash_change(zobrist::OPPONENT, &self.opponent)
+    }
 }
 
-fn find_bit_index_from_rank(occupied: u64, i: u64) -> u8 {
-    // Adapted from https://graphics.stanford.edu/~seander/bithacks.html#SelectPosFromMSBRank
-    let v = !occupied;
-    
-    let mut r = u64::from(v.count_ones()) - i;
-
-    let a: u64 =  v - ((v >> 1) & (!0u64/3));
-    let b: u64 = (a & (!0u64/5)) + ((a >> 2) & (!0u64/5));
-    let c: u64 = (b + (b >> 4)) & (!0u64/0x11);
-    let d: u64 = (c + (c >> 8)) & (!0u64/0x101);
-    let mut t: u64 = (d >> 32) + (d >> 48);
-
-    let mut s: u64 = 64;
-    s -= (t.wrapping_sub(r) & 256) >> 3; r -= t & (t.wrapping_sub(r) >> 8);
-    t  = (d >> (s - 16)) & 0xff;
-    s -= (t.wrapping_sub(r) & 256) >> 4; r -= t & (t.wrapping_sub(r) >> 8);
-    t  = (c >> (s - 8)) & 0xf;
-    s -= (t.wrapping_sub(r) & 256) >> 5; r -= t & (t.wrapping_sub(r) >> 8);
-    t  = (b >> (s - 4)) & 0x7;
-    s -= (t.wrapping_sub(r) & 256) >> 6; r -= t & (t.wrapping_sub(r) >> 8);
-    t  = (a >> (s - 2)) & 0x3;
-    s -= (t.wrapping_sub(r) & 256) >> 7; r -= t & (t.wrapping_sub(r) >> 8);
-    t  = (v >> (s - 1)) & 0x1;
-    s -= (t.wrapping_sub(r) & 256) >> 8;
-    s = 65 - s;
-
-    64 - s as u8
+impl<M: MapSize> PlayerUndo<M> {
+    fn record(player: &Player<M>) -> PlayerUndo<M> {
+        PlayerUndo {
+            energy: player.energy,
+            health: player.health,
+            unconstructed: player.unconstructed.clone(),
+            buildings: player.buildings,
+            occupied: player.occupied,
+            energy_towers: player.energy_towers,
+            missile_towers: player.missile_towers,
+            firing_tower: player.firing_tower,
+            missiles: player.missiles,
+            tesla_cooldowns: player.tesla_cooldowns.clone(),
+            iron_curtain_available: player.iron_curtain_available,
+            iron_curtain_remaining: player.iron_curtain_remaining
+        }
+    }
+
+    fn hash_change(&self, side: usize, player: &Player<M>) -> u64 {
+        zobrist::change(&self.energy, &player.energy, |&energy| zobrist::energy(side, energy))
+            ^ zobrist::change(&self.health, &player.health, |&health| zobrist::health(side, health))
+            ^ zobrist::change(&self.unconstructed[..], &player.unconstructed[..], |unconstructed| zobrist::unconstructed(side, unconstructed))
+            ^ zobrist::buildings_change::<M>(side, &self.buildings, &player.buildings)
+            ^ zobrist::energy_towers_change::<M>(side, self.energy_towers, player.energy_towers)
+            ^ zobrist::missile_towers_change::<M>(side, &self.missile_towers, self.firing_tower, &player.missile_towers, player.firing_tower)
+            ^ zobrist::missiles_change::<M>(side, &self.missiles, &player.missiles)
+            ^ zobrist::change(&self.tesla_cooldowns[..], &player.tesla_cooldowns[..], |teslas| zobrist::teslas(side, teslas))
+            ^ zobrist::change(&(self.iron_curtain_available, self.iron_curtain_remaining), &(player.iron_curtain_available, player.iron_curtain_remaining), |&(available, remaining)| zobrist::iron_curtain(side, available, remaining))
+    }
+
+    fn restore(self, player: &mut Player<M>) {
+        player.energy = self.energy;
+        player.health = self.health;
+        player.unconstructed = self.unconstructed;
+        player.buildings = self.buildings;
+        player.occupied = self.occupied;
+        player.energy_towers = self.energy_towers;
+        player.missile_towers = self.missile_towers;
+        player.firing_tower = self.firing_tower;
+        player.missiles = self.missiles;
+        player.tesla_cooldowns = self.tesla_cooldowns;
+        player.iron_curtain_available = self.iron_curtain_available;
+        player.iron_curtain_remaining = self.iron_curtain_remaining;
+    }
 }
 
-impl BitwiseGameState {
+impl<M: MapSize> BitwiseGameState<M> {
     pub fn new(
-        player: Player, opponent: Player,
-        round: u16
-    ) -> BitwiseGameState {
-        BitwiseGameState {
+        player: Player<M>, opponent: Player<M>,
+        round: u16,
+        config: GameConfig
+    ) -> BitwiseGameState<M> {
+        let mut state = BitwiseGameState {
             status: GameStatus::Continue,
             player, opponent,
-            round
-        }
+            round,
+            config,
+            hash: 0
+        };
+        state.update_status();
+        state.rehash();
+        state
     }
 
     /**
-     * This is to make things more comparable when writing tests, not
-     * for actual use in the engine.
+     * This is to make things more comparable when writing tests or
+     * checking replays, not for actual use in the engine.
      */
-    #[cfg(debug_assertions)]
     pub fn sort(&mut self) {
-        for i in 0..MISSILE_MAX_SINGLE_CELL {
-            for j in i+1..MISSILE_MAX_SINGLE_CELL {
-                let move_down1 = !self.player.missiles[i].0 & self.player.missiles[j].0;
-                self.player.missiles[i].0 |= move_down1;
-                self.player.missiles[j].0 &= !move_down1;
-
-                let move_down2 = !self.player.missiles[i].1 & self.player.missiles[j].1;
-                self.player.missiles[i].1 |= move_down2;
-                self.player.missiles[j].1 &= !move_down2;
-
-                let move_down3 = !self.opponent.missiles[i].0 & self.opponent.missiles[j].0;
-                self.opponent.missiles[i].0 |= move_down3;
-                self.opponent.missiles[j].0 &= !move_down3;
-
-                let move_down4 = !self.opponent.missiles[i].1 & self.opponent.missiles[j].1;
-                self.opponent.missiles[i].1 |= move_down4;
-                self.opponent.missiles[j].1 &= !move_down4;
+        let player_missiles = self.player.missiles.as_mut();
+        let opponent_missiles = self.opponent.missiles.as_mut();
+        for i in 0..player_missiles.len() {
+            for j in i+1..player_missiles.len() {
+                let move_down1 = !player_missiles[i].0 & player_missiles[j].0;
+                player_missiles[i].0 |= move_down1;
+                player_missiles[j].0 &= !move_down1;
+
+                let move_down2 = !player_missiles[i].1 & player_missiles[j].1;
+                player_missiles[i].1 |= move_down2;
+                player_missiles[j].1 &= !move_down2;
+
+                let move_down3 = !opponent_missiles[i].0 & opponent_missiles[j].0;
+                opponent_missiles[i].0 |= move_down3;
+                opponent_missiles[j].0 &= !move_down3;
+
+                let move_down4 = !opponent_missiles[i].1 & opponent_missiles[j].1;
+                opponent_missiles[i].1 |= move_down4;
+                opponent_missiles[j].1 &= !move_down4;
             }
         }
 
@@ -185,82 +337,101 @@ impl BitwiseGameState {
         }
     }
 
-    #[cfg(debug_assertions)]
-    pub fn sorted(&self) -> BitwiseGameState {
+    pub fn sorted(&self) -> BitwiseGameState<M> {
         let mut res = self.clone();
         res.sort();
         res
     }
 
-    fn update_iron_curtain(player: &mut Player, round: u16) {
-        if round != 0 && round % IRON_CURTAIN_UNLOCK_INTERVAL == 0 {
+    /**
+     * Exchanges the players, so that the state is seen from the
+     * opponent's side. Each player's missiles are kept relative to
+     * that player, so nothing on the board needs to be flipped.
+     */
+    pub fn swap(&mut self) {
+        mem::swap(&mut self.player, &mut self.opponent);
+        self.status = self.status.swapped();
+        self.rehash();
+    }
+
+    pub fn swapped(&self) -> BitwiseGameState<M> {
+        let mut res = self.clone();
+        res.swap();
+        res
+    }
+
+    fn update_iron_curtain(player: &mut Player<M>, round: u16, config: &GameConfig) {
+        if round != 0 && round.is_multiple_of(config.iron_curtain_unlock_interval) {
             player.iron_curtain_available = true;
         }
         player.iron_curtain_remaining = player.iron_curtain_remaining.saturating_sub(1);
     }
     
-    fn fire_teslas(player: &mut Player, opponent: &mut Player) {
-        BitwiseGameState::fire_single_players_teslas_without_cleanup(player, opponent);
-        BitwiseGameState::fire_single_players_teslas_without_cleanup(opponent, player);
+    fn fire_teslas(player: &mut Player<M>, opponent: &mut Player<M>, config: &GameConfig) {
+        BitwiseGameState::fire_single_players_teslas_without_cleanup(player, opponent, config);
+        BitwiseGameState::fire_single_players_teslas_without_cleanup(opponent, player, config);
 
         BitwiseGameState::update_tesla_activity(player);
         BitwiseGameState::update_tesla_activity(opponent);
     }
 
-    fn fire_single_players_teslas_without_cleanup(player: &mut Player, opponent: &mut Player) {
-        // It's technically more accurate to have this in, but for
-        // most practical purposes it's a moot point and it's faster
-        // without it.
-        //
-        // player.tesla_cooldowns.sort_unstable_by(|a, b| b.age.cmp(&a.age));
+    fn fire_single_players_teslas_without_cleanup(player: &mut Player<M>, opponent: &mut Player<M>, config: &GameConfig) {
+        // The game engine fires the oldest first. They fire in order of
+        // position here, which only matters when there isn't enough
+        // energy for all of them, and keeps the age out of play.
         for tesla in player.tesla_cooldowns.iter_mut() {
             tesla.age += 1;
             if tesla.cooldown > 0 {
                 tesla.cooldown -= 1;
-            } else if player.energy >= TESLA_FIRING_ENERGY && opponent.iron_curtain_remaining > 0 {
-                player.energy -= TESLA_FIRING_ENERGY;
-                tesla.cooldown = TESLA_COOLDOWN;
-            } else if player.energy >= TESLA_FIRING_ENERGY {
-                player.energy -= TESLA_FIRING_ENERGY;
-                tesla.cooldown = TESLA_COOLDOWN;
-
-                if tesla.pos.to_either_bitfield() & RIGHT_COL_MASK != 0 {
-                    opponent.health = opponent.health.saturating_sub(TESLA_DAMAGE);
-                }
+            } else if player.energy >= config.tesla_firing_energy && opponent.iron_curtain_remaining > 0 {
+                player.energy -= config.tesla_firing_energy;
+                tesla.cooldown = config.tesla_cooldown;
+            } else if player.energy >= config.tesla_firing_energy {
+                player.energy -= config.tesla_firing_energy;
+                tesla.cooldown = config.tesla_cooldown;
 
                 let x = tesla.pos.x();
                 let y = tesla.pos.y();
-                let missed_cells = (u32::from(SINGLE_MAP_WIDTH - x)).saturating_sub(2);
+
+                // Distances are counted from the tesla, and the
+                // opponent's base is one cell past the back of their
+                // half. Cells further away than the range are missed.
+                let distance_to_base = 2 * u32::from(M::SINGLE_MAP_WIDTH) - u32::from(x);
+                if distance_to_base <= u32::from(config.tesla_range) {
+                    opponent.health = opponent.health.saturating_sub(config.tesla_damage);
+                }
+                let missed_cells = (distance_to_base - 1).saturating_sub(u32::from(config.tesla_range))
+                    .min(u32::from(M::SINGLE_MAP_WIDTH));
                 
                 let top_row = y.saturating_sub(1);
-                let top_row_mask = ROW_MASKS[top_row as usize];
-                let mut destroy_mask = top_row_mask.wrapping_shl(missed_cells) & top_row_mask;
+                let top_row_mask = M::row_mask(top_row);
+                let mut destroy_mask = (top_row_mask << missed_cells) & top_row_mask;
 
-                let mut hits = 0;
-                for _ in 0..(if y == 0 || y == MAP_HEIGHT-1 { 2 } else { 3 }) {
+                let mut hits = M::Bits::ZERO;
+                for _ in 0..(if y == 0 || y == M::MAP_HEIGHT-1 { 2 } else { 3 }) {
                     hits |= destroy_mask & opponent.buildings[0];
                     destroy_mask &= !hits;
-                    destroy_mask <<= SINGLE_MAP_WIDTH;
+                    destroy_mask <<= u32::from(M::SINGLE_MAP_WIDTH);
                 }
                 BitwiseGameState::destroy_buildings(opponent, hits);
             }
         }
     }
 
-    fn move_and_collide_missiles(opponent: &mut Player, player_missiles: &mut [(u64, u64); MISSILE_MAX_SINGLE_CELL]) {
-        let mut destroyed = 0;
-        let mut damaging = 0;
+    fn move_and_collide_missiles(opponent: &mut Player<M>, player_missiles: &mut M::Missiles, config: &GameConfig) {
+        let mut destroyed = M::Bits::ZERO;
+        let mut damaging = M::Bits::ZERO;
         for _ in 0..MISSILE_SPEED {
-            for missile in player_missiles.iter_mut() {
-                let swapping_sides = if opponent.iron_curtain_remaining > 0 { 0 } else { missile.0 & RIGHT_COL_MASK };
-                let about_to_hit_opponent = missile.1 & LEFT_COL_MASK;
+            for missile in player_missiles.as_mut().iter_mut() {
+                let swapping_sides = if opponent.iron_curtain_remaining > 0 { M::Bits::ZERO } else { missile.0 & M::RIGHT_COL_MASK };
+                let about_to_hit_opponent = missile.1 & M::LEFT_COL_MASK;
 
-                missile.0 = (missile.0 & !RIGHT_COL_MASK) << 1;
-                missile.1 = ((missile.1 & !LEFT_COL_MASK) >> 1) | swapping_sides;
+                missile.0 = (missile.0 & !M::RIGHT_COL_MASK) << 1;
+                missile.1 = ((missile.1 & !M::LEFT_COL_MASK) >> 1) | swapping_sides;
 
                 damaging = (damaging << 1) | about_to_hit_opponent;
 
-                let mut hits = 0;
+                let mut hits = M::Bits::ZERO;
                 for health_tier in (0..DEFENCE_HEALTH).rev() {
                     hits = opponent.buildings[health_tier] & missile.1;
                     missile.1 &= !hits;
@@ -269,14 +440,14 @@ impl BitwiseGameState {
                 destroyed |= hits;
             }
         }
-        let damage = damaging.count_ones() as u8 * MISSILE_DAMAGE;
+        let damage = damaging.count_ones() as u8 * config.missile_damage;
         opponent.health = opponent.health.saturating_sub(damage);
 
         BitwiseGameState::destroy_buildings(opponent, destroyed);
         BitwiseGameState::update_tesla_activity(opponent);
     }
 
-    fn destroy_buildings(buildings: &mut Player, hit_mask: u64) {
+    fn destroy_buildings(buildings: &mut Player<M>, hit_mask: M::Bits) {
         let deconstruct_mask = !hit_mask;
         
         buildings.energy_towers &= deconstruct_mask;
@@ -289,14 +460,14 @@ impl BitwiseGameState {
         buildings.occupied &= deconstruct_mask;
     }
 
-    fn update_tesla_activity(buildings: &mut Player) {
+    fn update_tesla_activity(buildings: &mut Player<M>) {
         let occupied = buildings.occupied;
-        buildings.tesla_cooldowns.retain(|t| (t.pos.to_either_bitfield() & occupied) != 0);
+        buildings.tesla_cooldowns.retain(|t| (t.pos.to_either_bitfield::<M>() & occupied) != M::Bits::ZERO);
     }
     
     
-    fn add_energy(player: &mut Player) {
-        player.energy += player.energy_generated();
+    fn add_energy(player: &mut Player<M>, config: &GameConfig) {
+        player.energy += player.energy_generated(config);
     }
 
     fn update_status(&mut self) {
@@ -312,31 +483,55 @@ impl BitwiseGameState {
 
 }
 
-impl Player {
+impl<M: MapSize> Player<M> {
     pub fn count_teslas(&self) -> usize {
         self.tesla_cooldowns.len()
             + self.unconstructed.iter().filter(|t| t.building_type == BuildingType::Tesla).count()
     }
 
-    pub fn empty() -> Player {
+    /**
+     * Adds a finished tesla. The teslas are kept in order of position,
+     * which is the order they fire in, so that states with the same
+     * teslas always have them in the same order.
+     */
+    pub fn add_tesla(&mut self, tesla: TeslaCooldown) -> Result<(), CapacityError<TeslaCooldown>> {
+        let index = self.tesla_cooldowns.iter()
+            .position(|t| t.pos > tesla.pos)
+            .unwrap_or_else(|| self.tesla_cooldowns.len());
+        self.tesla_cooldowns.try_insert(index, tesla)
+    }
+
+    pub fn empty() -> Player<M> {
         Player {
             health: 0,
             energy: 0,
             unconstructed: ArrayVec::new(),
-            buildings: [0; DEFENCE_HEALTH],
-            occupied: 0,
-            energy_towers: 0,
-            missile_towers: [0; MISSILE_COOLDOWN_STATES],
+            buildings: [M::Bits::ZERO; DEFENCE_HEALTH],
+            occupied: M::Bits::ZERO,
+            energy_towers: M::Bits::ZERO,
+            missile_towers: [M::Bits::ZERO; MISSILE_COOLDOWN_STATES],
             firing_tower: 0,
-            missiles: [(0,0); MISSILE_MAX_SINGLE_CELL],
+            missiles: M::Missiles::default(),
             tesla_cooldowns: ArrayVec::new(),
             iron_curtain_available: false,
             iron_curtain_remaining: 0,
         }
     }
 
-    pub fn energy_generated(&self) -> u16 {
-        ENERGY_GENERATED_BASE + self.energy_towers.count_ones() as u16 * ENERGY_GENERATED_TOWER
+    fn zobrist_hash(&self, side: usize) -> u64 {
+        zobrist::energy(side, self.energy)
+            ^ zobrist::health(side, self.health)
+            ^ zobrist::unconstructed(side, &self.unconstructed)
+            ^ zobrist::buildings::<M>(side, &self.buildings)
+            ^ zobrist::energy_towers::<M>(side, self.energy_towers)
+            ^ zobrist::missile_towers::<M>(side, &self.missile_towers, self.firing_tower)
+            ^ zobrist::missiles::<M>(side, &self.missiles)
+            ^ zobrist::teslas(side, &self.tesla_cooldowns)
+            ^ zobrist::iron_curtain(side, self.iron_curtain_available, self.iron_curtain_remaining)
+    }
+
+    pub fn energy_generated(&self, config: &GameConfig) -> u16 {
+        config.energy_generated_base + self.energy_towers.count_ones() as u16 * config.energy_generated_tower
     }
 
     pub fn has_max_teslas(&self) -> bool {
@@ -347,43 +542,123 @@ impl Player {
         self.iron_curtain_available && self.iron_curtain_remaining == 0
     }
 
-    pub fn can_build_iron_curtain_in(&self, round: u16, moves: u8) -> bool {
-        let unlocks = round % IRON_CURTAIN_UNLOCK_INTERVAL > round + u16::from(moves) % IRON_CURTAIN_UNLOCK_INTERVAL;
+    pub fn can_build_iron_curtain_in(&self, round: u16, moves: u8, config: &GameConfig) -> bool {
+        let unlocks = round % config.iron_curtain_unlock_interval > round + u16::from(moves) % config.iron_curtain_unlock_interval;
         (self.iron_curtain_available || unlocks) && self.iron_curtain_remaining.saturating_sub(moves) == 0
     }
 
-    pub fn unoccupied_cell_count(&self) -> usize { self.occupied.count_zeros() as usize }
+    /**
+     * Every command that the player can play now. These are the same
+     * commands that `validate_command` accepts.
+     */
+    pub fn legal_commands(&self, config: &GameConfig) -> LegalCommands<M> {
+        let build_cells = !self.occupied & M::ALL_CELLS_MASK;
+        let mut buildings = ArrayVec::new();
+        if build_cells != M::Bits::ZERO {
+            for &building in &BuildingType::all() {
+                if config.price(building) <= self.energy && (building != BuildingType::Tesla || !self.has_max_teslas()) {
+                    buildings.push(building);
+                }
+            }
+        }
+        LegalCommands {
+            buildings,
+            build_cells,
+            deconstruct_cells: self.occupied,
+            iron_curtain: self.can_build_iron_curtain() && self.energy >= config.iron_curtain_price
+        }
+    }
+
+    /**
+     * The legal commands that are worth trying. Deconstructing is only
+     * worth considering when there's no space left to build on, and
+     * with `energy_cutoff`, energy towers are left out once the player
+     * has plenty of energy.
+     */
+    pub fn sensible_commands(&self, config: &GameConfig, energy_cutoff: bool) -> LegalCommands<M> {
+        let mut commands = self.legal_commands(config);
+        if energy_cutoff && !self.needs_energy(config) {
+            commands.buildings.retain(|&mut b| b != BuildingType::Energy);
+        }
+        if commands.build_cells != M::Bits::ZERO {
+            commands.deconstruct_cells = M::Bits::ZERO;
+        }
+        commands
+    }
+
+    pub fn needs_energy(&self, config: &GameConfig) -> bool {
+        self.energy_generated(config) <= ENERGY_PRODUCTION_CUTOFF ||
+            self.energy <= ENERGY_STORAGE_CUTOFF
+    }
+
+    /**
+     * Checks that the player can play the command now, with the same
+     * rules as the game engine.
+     */
+    pub fn validate_command(&self, command: Command, config: &GameConfig) -> Result<(), CommandError> {
+        let in_bounds = |p: Point| if p.x() < M::SINGLE_MAP_WIDTH && p.y() < M::MAP_HEIGHT { Ok(()) } else { Err(CommandError::OutOfBounds(p)) };
+        let occupied = |p: Point| self.occupied & p.to_either_bitfield::<M>() != M::Bits::ZERO;
+        let afford = |price: u16| if self.energy >= price { Ok(()) } else { Err(CommandError::NotEnoughEnergy { price, energy: self.energy }) };
+
+        match command {
+            Command::Nothing => Ok(()),
+            Command::Build(p, b) => {
+                in_bounds(p)?;
+                if occupied(p) {
+                    return Err(CommandError::CellOccupied(p));
+                }
+                afford(config.price(b))?;
+                if b == BuildingType::Tesla && self.has_max_teslas() {
+                    return Err(CommandError::TooManyTeslas);
+                }
+                Ok(())
+            },
+            Command::IronCurtain => {
+                if !self.can_build_iron_curtain() {
+                    return Err(CommandError::IronCurtainUnavailable);
+                }
+                afford(config.iron_curtain_price)
+            },
+            Command::Deconstruct(p) => {
+                in_bounds(p)?;
+                if !occupied(p) {
+                    return Err(CommandError::NothingToDeconstruct(p));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    pub fn unoccupied_cell_count(&self) -> usize { (!self.occupied & M::ALL_CELLS_MASK).count_ones() as usize }
     pub fn location_of_unoccupied_cell(&self, i: usize) -> Point  {
-        let bit = find_bit_index_from_rank(self.occupied, i as u64);
-        let point = Point { index: bit };
-        debug_assert!(point.to_either_bitfield() & self.occupied == 0);
+        let bit = (!self.occupied & M::ALL_CELLS_MASK).select(i as u32);
+        let point = Point::from_bit_index::<M>(bit);
+        debug_assert!(point.to_either_bitfield::<M>() & self.occupied == M::Bits::ZERO);
+        point
+    }
+
+    pub fn occupied_cell_count(&self) -> usize { self.occupied.count_ones() as usize }
+    pub fn location_of_occupied_cell(&self, i: usize) -> Point  {
+        let bit = self.occupied.select(i as u32);
+        let point = Point::from_bit_index::<M>(bit);
+        debug_assert!(point.to_either_bitfield::<M>() & self.occupied != M::Bits::ZERO);
         point
     }
 
 
-    fn perform_command(&mut self, command: Command) {
+    fn perform_command(&mut self, command: Command, config: &GameConfig) {
         match command {
             Command::Nothing => {},
             Command::Build(p, b) => {
-                let bitfield = p.to_either_bitfield();
-
-                let price = match b {
-                    BuildingType::Attack => MISSILE_PRICE,
-                    BuildingType::Defence => DEFENCE_PRICE,
-                    BuildingType::Energy => ENERGY_PRICE,
-                    BuildingType::Tesla => TESLA_PRICE,
-                };
-                let construction_time = match b {
-                    BuildingType::Attack => MISSILE_CONSTRUCTION_TIME,
-                    BuildingType::Defence => DEFENCE_CONSTRUCTION_TIME,
-                    BuildingType::Energy => ENERGY_CONSTRUCTION_TIME,
-                    BuildingType::Tesla => TESLA_CONSTRUCTION_TIME,
-                };
+                let bitfield = p.to_either_bitfield::<M>();
+
+                let price = config.price(b);
+                let construction_time = config.construction_time(b);
 
                 // This is used internally. I should not be making
                 // invalid moves!
-                debug_assert!(self.buildings[0] & bitfield == 0);
-                debug_assert!(p.x() < FULL_MAP_WIDTH && p.y() < MAP_HEIGHT);
+                debug_assert!(self.buildings[0] & bitfield == M::Bits::ZERO);
+                debug_assert!(p.x() < M::SINGLE_MAP_WIDTH && p.y() < M::MAP_HEIGHT);
                 debug_assert!(self.energy >= price);
                 debug_assert!(b != BuildingType::Tesla ||
                               self.count_teslas() < TESLA_MAX);
@@ -398,11 +673,21 @@ impl Player {
             },
             Command::IronCurtain => {
                 debug_assert!(self.iron_curtain_available);
-                debug_assert!(self.energy >= IRON_CURTAIN_PRICE);
+                debug_assert!(self.energy >= config.iron_curtain_price);
 
-                self.energy -= IRON_CURTAIN_PRICE;
+                self.energy -= config.iron_curtain_price;
                 self.iron_curtain_available = false;
-                self.iron_curtain_remaining = IRON_CURTAIN_DURATION;
+                self.iron_curtain_remaining = config.iron_curtain_duration;
+            },
+            Command::Deconstruct(p) => {
+                let bitfield = p.to_either_bitfield::<M>();
+
+                debug_assert!(self.occupied & bitfield != M::Bits::ZERO);
+
+                BitwiseGameState::destroy_buildings(self, bitfield);
+                self.unconstructed.retain(|b| b.pos != p);
+                self.tesla_cooldowns.retain(|t| t.pos != p);
+                self.energy += config.deconstruct_energy;
             }
         }
     }
@@ -415,7 +700,7 @@ impl Player {
                 let health = if building_type == BuildingType::Defence { DEFENCE_HEALTH } else { 1 };
 
                 let pos = self.unconstructed[i].pos;
-                let bitfield = pos.to_either_bitfield();
+                let bitfield = pos.to_either_bitfield::<M>();
 
                 for health_tier in 0..health {
                     self.buildings[health_tier] |= bitfield;
@@ -427,11 +712,11 @@ impl Player {
                     self.missile_towers[self.firing_tower] |= bitfield;
                 }
                 if building_type == BuildingType::Tesla {
-                    self.tesla_cooldowns.push(TeslaCooldown {
+                    self.add_tesla(TeslaCooldown {
                         pos,
                         cooldown: 0,
                         age: 0
-                    });
+                    }).expect("More teslas than the engine has room for");
                 }
 
                 buildings_len -= 1;
@@ -445,7 +730,7 @@ impl Player {
 
     fn add_missiles(&mut self) {
         let mut missiles = self.missile_towers[self.firing_tower];
-        for mut tier in &mut self.missiles {
+        for tier in self.missiles.as_mut() {
             let setting = !tier.0 & missiles;
             tier.0 |= setting;
             missiles &= !setting;
@@ -453,27 +738,27 @@ impl Player {
         self.firing_tower = (self.firing_tower + 1) % MISSILE_COOLDOWN_STATES;
     }
 
-    fn any_missile_towers(&self) -> u64 {
-        self.missile_towers.iter().fold(0, |acc, next| acc | next)
+    fn any_missile_towers(&self) -> M::Bits {
+        self.missile_towers.iter().fold(M::Bits::ZERO, |acc, &next| acc | next)
     }
 
     pub fn count_attack_towers_in_row(&self, y: u8) -> u16 {
-        let mask = ROW_MASKS[y as usize];
+        let mask = M::row_mask(y);
         (self.any_missile_towers() & mask).count_ones() as u16
     }
 
     pub fn count_energy_towers_in_row(&self, y: u8) -> u16 {
-        let mask = ROW_MASKS[y as usize];
+        let mask = M::row_mask(y);
         (self.energy_towers & mask).count_ones() as u16
     }
 
     pub fn count_healthy_defence_in_row(&self, y: u8) -> u16 {
-        let mask = ROW_MASKS[y as usize];
+        let mask = M::row_mask(y);
         (self.buildings[1] & mask).count_ones() as u16
     }
 
     pub fn count_towers_in_row(&self, y: u8) -> u16 {
-        let mask = ROW_MASKS[y as usize];
+        let mask = M::row_mask(y);
         (self.occupied & mask).count_ones() as u16
     }
 
@@ -0,0 +1,102 @@
+use engine::command::BuildingType;
+use engine::geometry::Point;
+use engine::constants::*;
+use engine::bitboard::{Bitboard, MapSize};
+use engine::bitwise_engine::{BitwiseGameState, Player};
+
+/**
+ * What is in one cell of the map, worked out from the bitfields. This
+ * is for reports and debugging output, not for use in the search.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    pub building: Option<CellBuilding>,
+    /// Missiles fired by the player that owns this half of the map.
+    pub outgoing_missiles: u8,
+    /// Missiles fired by the other player.
+    pub incoming_missiles: u8
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CellBuilding {
+    pub building_type: BuildingType,
+    /// Zero once construction has finished.
+    pub construction_time_left: u8,
+    /// The number of missile hits the building can still take.
+    pub health_tiers: u8,
+    /// Rounds until a missile tower or tesla can fire again.
+    pub weapon_cooldown: u8,
+    /// Rounds since a tesla finished construction.
+    pub age: u16
+}
+
+impl CellBuilding {
+    /// Buildings under construction can't be hit yet, so they have no
+    /// health.
+    pub fn is_under_construction(&self) -> bool {
+        self.health_tiers == 0
+    }
+}
+
+impl<M: MapSize> BitwiseGameState<M> {
+    /**
+     * The cell at `point`, in the own coordinates of the player whose
+     * half it's on.
+     */
+    pub fn cell(&self, is_player_half: bool, point: Point) -> Cell {
+        let (owner, visitor) = if is_player_half {
+            (&self.player, &self.opponent)
+        } else {
+            (&self.opponent, &self.player)
+        };
+        let bitfield = point.to_either_bitfield::<M>();
+
+        Cell {
+            building: owner.building_at(point),
+            outgoing_missiles: owner.missiles.as_ref().iter()
+                .filter(|&&(own_side, _)| own_side & bitfield != M::Bits::ZERO)
+                .count() as u8,
+            incoming_missiles: visitor.missiles.as_ref().iter()
+                .filter(|&&(_, opponent_side)| opponent_side & bitfield != M::Bits::ZERO)
+                .count() as u8
+        }
+    }
+}
+
+impl<M: MapSize> Player<M> {
+    pub fn building_at(&self, point: Point) -> Option<CellBuilding> {
+        let bitfield = point.to_either_bitfield::<M>();
+        if self.occupied & bitfield == M::Bits::ZERO {
+            return None;
+        }
+
+        if let Some(unconstructed) = self.unconstructed.iter().find(|b| b.pos == point) {
+            return Some(CellBuilding {
+                building_type: unconstructed.building_type,
+                construction_time_left: unconstructed.construction_time_left,
+                health_tiers: 0,
+                weapon_cooldown: 0,
+                age: 0
+            });
+        }
+
+        let health_tiers = self.buildings.iter().filter(|&&tier| tier & bitfield != M::Bits::ZERO).count() as u8;
+        let building = |building_type, weapon_cooldown, age| Some(CellBuilding {
+            building_type,
+            construction_time_left: 0,
+            health_tiers,
+            weapon_cooldown,
+            age
+        });
+
+        if self.energy_towers & bitfield != M::Bits::ZERO {
+            building(BuildingType::Energy, 0, 0)
+        } else if let Some(tier) = self.missile_towers.iter().position(|&tier| tier & bitfield != M::Bits::ZERO) {
+            building(BuildingType::Attack, ((tier + MISSILE_COOLDOWN_STATES - self.firing_tower) % MISSILE_COOLDOWN_STATES) as u8, 0)
+        } else if let Some(tesla) = self.tesla_cooldowns.iter().find(|t| t.pos == point) {
+            building(BuildingType::Tesla, tesla.cooldown, tesla.age)
+        } else {
+            building(BuildingType::Defence, 0, 0)
+        }
+    }
+}
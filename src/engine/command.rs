@@ -1,12 +1,15 @@
+use std::error::Error;
 use std::fmt;
 use super::constants::*;
+use super::config::GameConfig;
 use super::geometry::Point;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Command {
     Nothing,
     Build(Point, BuildingType),
-    IronCurtain
+    IronCurtain,
+    Deconstruct(Point)
 }
 
 impl fmt::Display for Command {
@@ -14,23 +17,53 @@ impl fmt::Display for Command {
         match *self {
             Command::Nothing => write!(f, ""),
             Command::Build(p, b) => write!(f, "{},{},{}", p.x(), p.y(), b as u8),
-            Command::IronCurtain => write!(f, "0,0,5")
+            Command::IronCurtain => write!(f, "0,0,5"),
+            Command::Deconstruct(p) => write!(f, "{},{},3", p.x(), p.y())
         }
     }
 }
 
 impl Command {
-    pub fn cant_build_yet(self, energy: u16) -> bool {
+    pub fn cant_build_yet(self, energy: u16, config: &GameConfig) -> bool {
         use self::Command::*;
 
         match self {
             Nothing => false,
-            Build(_, b) => b.cant_build_yet(energy),
-            IronCurtain => energy < IRON_CURTAIN_PRICE
+            Build(_, b) => b.cant_build_yet(energy, config),
+            IronCurtain => energy < config.iron_curtain_price,
+            Deconstruct(_) => false
         }
     }
 }
 
+/**
+ * Why a command can't be played. The game engine treats an invalid
+ * command as doing nothing for that round.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandError {
+    OutOfBounds(Point),
+    CellOccupied(Point),
+    NothingToDeconstruct(Point),
+    NotEnoughEnergy { price: u16, energy: u16 },
+    TooManyTeslas,
+    IronCurtainUnavailable
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CommandError::OutOfBounds(p) => write!(f, "({}, {}) is off the player's side of the map", p.x(), p.y()),
+            CommandError::CellOccupied(p) => write!(f, "there's already a building at ({}, {})", p.x(), p.y()),
+            CommandError::NothingToDeconstruct(p) => write!(f, "there's no building to deconstruct at ({}, {})", p.x(), p.y()),
+            CommandError::NotEnoughEnergy { price, energy } => write!(f, "it costs {} energy, but the player only has {}", price, energy),
+            CommandError::TooManyTeslas => write!(f, "the player already has {} teslas", TESLA_MAX),
+            CommandError::IronCurtainUnavailable => write!(f, "the iron curtain isn't available")
+        }
+    }
+}
+
+impl Error for CommandError {}
 
 #[repr(u8)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -49,18 +82,10 @@ impl BuildingType {
 
     pub fn from_u8(id: u8) -> Option<BuildingType> {
         use std::mem;
-        if id <= 4 && id != 3 { Some(unsafe { mem::transmute(id) }) } else { None }
+        if id <= 4 && id != 3 { Some(unsafe { mem::transmute::<u8, BuildingType>(id) }) } else { None }
     }
 
-    pub fn cant_build_yet(self, energy: u16) -> bool {
-        use self::BuildingType::*;
-
-        let required = match self {
-            Defence => DEFENCE_PRICE,
-            Attack => MISSILE_PRICE,
-            Energy => ENERGY_PRICE,
-            Tesla => TESLA_PRICE
-        };
-        energy < required
+    pub fn cant_build_yet(self, energy: u16, config: &GameConfig) -> bool {
+        energy < config.price(self)
     }
 }
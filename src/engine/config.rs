@@ -0,0 +1,98 @@
+use engine::command::BuildingType;
+use engine::constants::*;
+
+/**
+ * The rule values that can change between versions of the game
+ * without changing the shape of the bitwise engine's state.
+ *
+ * Values that determine the size of the engine's arrays (map size,
+ * defence health tiers, missile speed and cooldown) are still
+ * compile time constants, so a config that disagrees with them is
+ * rejected when it's read.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameConfig {
+    pub max_rounds: u16,
+
+    pub energy_generated_base: u16,
+    pub energy_generated_tower: u16,
+
+    pub missile_damage: u8,
+    pub missile_price: u16,
+    pub missile_construction_time: u8,
+
+    pub defence_price: u16,
+    pub defence_construction_time: u8,
+
+    pub tesla_cooldown: u8,
+    pub tesla_firing_energy: u16,
+    pub tesla_damage: u8,
+    pub tesla_price: u16,
+    pub tesla_construction_time: u8,
+    /// How many cells away a tesla can hit, which is the same on
+    /// every map size.
+    pub tesla_range: u8,
+
+    pub energy_price: u16,
+    pub energy_construction_time: u8,
+
+    pub iron_curtain_price: u16,
+    pub iron_curtain_unlock_interval: u16,
+    pub iron_curtain_duration: u8,
+
+    pub deconstruct_energy: u16,
+}
+
+impl Default for GameConfig {
+    fn default() -> GameConfig {
+        GameConfig {
+            max_rounds: MAX_MOVES,
+
+            energy_generated_base: ENERGY_GENERATED_BASE,
+            energy_generated_tower: ENERGY_GENERATED_TOWER,
+
+            missile_damage: MISSILE_DAMAGE,
+            missile_price: MISSILE_PRICE,
+            missile_construction_time: MISSILE_CONSTRUCTION_TIME,
+
+            defence_price: DEFENCE_PRICE,
+            defence_construction_time: DEFENCE_CONSTRUCTION_TIME,
+
+            tesla_cooldown: TESLA_COOLDOWN,
+            tesla_firing_energy: TESLA_FIRING_ENERGY,
+            tesla_damage: TESLA_DAMAGE,
+            tesla_price: TESLA_PRICE,
+            tesla_construction_time: TESLA_CONSTRUCTION_TIME,
+            tesla_range: TESLA_RANGE,
+
+            energy_price: ENERGY_PRICE,
+            energy_construction_time: ENERGY_CONSTRUCTION_TIME,
+
+            iron_curtain_price: IRON_CURTAIN_PRICE,
+            iron_curtain_unlock_interval: IRON_CURTAIN_UNLOCK_INTERVAL,
+            iron_curtain_duration: IRON_CURTAIN_DURATION,
+
+            deconstruct_energy: DECONSTRUCT_ENERGY,
+        }
+    }
+}
+
+impl GameConfig {
+    pub fn price(&self, building: BuildingType) -> u16 {
+        match building {
+            BuildingType::Defence => self.defence_price,
+            BuildingType::Attack => self.missile_price,
+            BuildingType::Energy => self.energy_price,
+            BuildingType::Tesla => self.tesla_price,
+        }
+    }
+
+    pub fn construction_time(&self, building: BuildingType) -> u8 {
+        match building {
+            BuildingType::Defence => self.defence_construction_time,
+            BuildingType::Attack => self.missile_construction_time,
+            BuildingType::Energy => self.energy_construction_time,
+            BuildingType::Tesla => self.tesla_construction_time,
+        }
+    }
+}
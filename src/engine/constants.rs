@@ -1,14 +1,12 @@
-pub const FULL_MAP_WIDTH: u8 = 16;
-pub const SINGLE_MAP_WIDTH: u8 = FULL_MAP_WIDTH/2;
-pub const MAP_HEIGHT: u8 = 8;
-
 pub const MAX_MOVES: u16 = 400;
 pub const INIT_SEED: [u8;16] = [0x7b, 0x6a, 0xe1, 0xf4, 0x41, 0x3c, 0xe9, 0x0f, 0x67, 0x81, 0x67, 0x99, 0x77, 0x0a, 0x6b, 0xda];
 
+// The rule values below are the defaults for GameConfig. The ones
+// used for array sizes can't be changed by the config.
+
 pub const MISSILE_COOLDOWN: usize = 3;
 pub const MISSILE_COOLDOWN_STATES: usize = MISSILE_COOLDOWN+1;
 pub const MISSILE_SPEED: usize = 2;
-pub const MISSILE_MAX_SINGLE_CELL: usize = SINGLE_MAP_WIDTH as usize / MISSILE_SPEED;
 pub const MISSILE_DAMAGE: u8 = 5;
 pub const MISSILE_PRICE: u16 = 30;
 pub const MISSILE_CONSTRUCTION_TIME: u8 = 1;
@@ -23,6 +21,7 @@ pub const TESLA_FIRING_ENERGY: u16 = 100;
 pub const TESLA_DAMAGE: u8 = 20;
 pub const TESLA_PRICE: u16 = 100;
 pub const TESLA_CONSTRUCTION_TIME: u8 = 10;
+pub const TESLA_RANGE: u8 = 9;
 
 pub const ENERGY_GENERATED_BASE: u16 = 5;
 pub const ENERGY_GENERATED_TOWER: u16 = 3;
@@ -35,12 +34,13 @@ pub const IRON_CURTAIN_DURATION: u8 = 6;
 
 pub const DECONSTRUCT_ENERGY: u16 = 5;
 
-pub const MAX_CONCURRENT_CONSTRUCTION: usize = 6; //2 teslas, and 3 of anything else, 1 extra because it's push here then update construction times
+// 2 teslas, and 3 of anything else, 1 extra because it's push here
+// then update construction times. Configs with longer construction
+// times are rejected when they're read, since they could overflow it.
+pub const MAX_CONCURRENT_CONSTRUCTION: usize = 6;
 
 
 pub const NUMBER_OF_BUILDING_TYPES: usize = 4;
-pub const NUMBER_OF_MAP_POSITIONS: usize = SINGLE_MAP_WIDTH as usize * MAP_HEIGHT as usize;
-pub const NUMBER_OF_POSSIBLE_MOVES: usize = NUMBER_OF_MAP_POSITIONS * NUMBER_OF_BUILDING_TYPES + 2;
 
 
 #[cfg(not(feature = "reduced-time"))]
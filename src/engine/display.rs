@@ -0,0 +1,117 @@
+use engine::command::BuildingType;
+use engine::geometry::Point;
+use engine::bitwise_engine::{BitwiseGameState, Player};
+use engine::bitboard::MapSize;
+use engine::cell::Cell;
+
+use std::fmt;
+
+const RESET: &str = "\x1b[0m";
+const DIM: &str = "\x1b[2m";
+const RED: &str = "\x1b[31m";
+const GREEN: &str = "\x1b[32m";
+const YELLOW: &str = "\x1b[33m";
+const BLUE: &str = "\x1b[34m";
+const MAGENTA: &str = "\x1b[35m";
+const CYAN: &str = "\x1b[36m";
+
+/**
+ * Draws the state as text. Each cell is three characters:
+ *
+ * - The building: `D`efence, `A`ttack, `E`nergy or `T`esla, in lower
+ *   case while it's under construction, or `.` for an empty cell.
+ * - A number for the building: the construction time left while it's
+ *   being built, the health tiers of a defence, or the cooldown of an
+ *   attack tower or tesla. `+` is anything above 9.
+ * - The missiles in the cell: `>` for player A's, `<` for player B's
+ *   and `*` for more than one.
+ *
+ * Use `{:#}` to draw it with terminal colours.
+ */
+impl<M: MapSize> fmt::Display for BitwiseGameState<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let colour = f.alternate();
+        writeln!(f, "Round {} ({:?})", self.round, self.status)?;
+        writeln!(f, "{}", player_summary('A', &self.player, colour))?;
+        writeln!(f, "{}", player_summary('B', &self.opponent, colour))?;
+        for line in board_lines(self, colour) {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+fn player_summary<M: MapSize>(player_type: char, player: &Player<M>, colour: bool) -> String {
+    let iron_curtain = if player.iron_curtain_remaining > 0 {
+        let status = format!("iron curtain active for {} rounds", player.iron_curtain_remaining);
+        if colour { format!("{}{}{}", CYAN, status, RESET) } else { status }
+    } else if player.iron_curtain_available {
+        "iron curtain available".to_string()
+    } else {
+        "no iron curtain".to_string()
+    };
+    format!("{}: health {}, energy {}, {}", player_type, player.health, player.energy, iron_curtain)
+}
+
+/**
+ * Just the map, one line per row, in the same format as `Display`.
+ */
+pub fn board_lines<M: MapSize>(state: &BitwiseGameState<M>, colour: bool) -> Vec<String> {
+    (0..M::MAP_HEIGHT).map(|y| {
+        (0..M::FULL_MAP_WIDTH).map(|x| {
+            let is_player_half = x < M::SINGLE_MAP_WIDTH;
+            let cell = state.cell(is_player_half, Point::from_map_coordinates::<M>(x, y));
+            format_cell(&cell, is_player_half, colour)
+        }).collect::<Vec<_>>().join("")
+    }).collect()
+}
+
+fn format_cell(cell: &Cell, is_player_half: bool, colour: bool) -> String {
+    let (building, detail, building_colour) = match cell.building {
+        None => ('.', ' ', DIM),
+        Some(b) => {
+            let (c, building_colour) = match b.building_type {
+                BuildingType::Defence => ('D', GREEN),
+                BuildingType::Attack => ('A', RED),
+                BuildingType::Energy => ('E', YELLOW),
+                BuildingType::Tesla => ('T', MAGENTA)
+            };
+            let under_construction = b.is_under_construction();
+            let number = match b.building_type {
+                _ if under_construction => Some(b.construction_time_left),
+                BuildingType::Defence => Some(b.health_tiers),
+                BuildingType::Attack | BuildingType::Tesla => Some(b.weapon_cooldown),
+                BuildingType::Energy => None
+            };
+            let detail = match number {
+                None => ' ',
+                Some(n) if n > 9 => '+',
+                Some(n) => (b'0' + n) as char
+            };
+            if under_construction {
+                (c.to_ascii_lowercase(), detail, DIM)
+            } else {
+                (c, detail, building_colour)
+            }
+        }
+    };
+
+    let (a_missiles, b_missiles) = if is_player_half {
+        (cell.outgoing_missiles, cell.incoming_missiles)
+    } else {
+        (cell.incoming_missiles, cell.outgoing_missiles)
+    };
+    let missiles = match (a_missiles, b_missiles) {
+        (0, 0) => ' ',
+        (1, 0) => '>',
+        (0, 1) => '<',
+        _ => '*'
+    };
+
+    if colour {
+        let missile_colour = if b_missiles == 0 { BLUE } else if a_missiles == 0 { RED } else { MAGENTA };
+        format!("{}{}{}{}{}{}{}", building_colour, building, detail, RESET, missile_colour, missiles, RESET)
+    } else {
+        format!("{}{}{}", building, detail, missiles)
+    }
+}
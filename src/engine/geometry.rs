@@ -1,43 +1,56 @@
-use engine::constants::*;
+use engine::bitboard::{Bitboard, MapSize};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Point {
-    pub index: u8
+    x: u8,
+    y: u8
 }
 
 impl Point {
+    /**
+     * Creates a point from coordinates on the player's own half of
+     * the map, where x=0 is the back column.
+     */
     pub fn new(x: u8, y: u8) -> Point {
-        let flipped_x = if x >= SINGLE_MAP_WIDTH {
-            FULL_MAP_WIDTH - x - 1
+        Point { x, y }
+    }
+
+    /**
+     * Creates a point from coordinates on the full map, as they
+     * appear in the state file. Points on the right half are mirrored
+     * into that player's own coordinates.
+     */
+    pub fn from_map_coordinates<M: MapSize>(x: u8, y: u8) -> Point {
+        let flipped_x = if x >= M::SINGLE_MAP_WIDTH {
+            M::FULL_MAP_WIDTH - x - 1
         } else {
             x
         };
-        Point {
-            index: y * SINGLE_MAP_WIDTH + flipped_x
-        }
+        Point::new(flipped_x, y)
     }
 
-    pub fn new_index(index: u8) -> Point {
+    pub fn from_bit_index<M: MapSize>(index: u8) -> Point {
         Point {
-            index
+            x: index % M::SINGLE_MAP_WIDTH,
+            y: index / M::SINGLE_MAP_WIDTH
         }
     }
 
-    pub fn new_double_bitfield(x: u8, y: u8, is_left_player: bool) -> (u64, u64) {
-        let bitfield = Point::new(x, y).to_either_bitfield();
-        if (x >= SINGLE_MAP_WIDTH) == is_left_player {
-            (0, bitfield)
+    pub fn new_double_bitfield<M: MapSize>(x: u8, y: u8, is_left_player: bool) -> (M::Bits, M::Bits) {
+        let bitfield = Point::from_map_coordinates::<M>(x, y).to_either_bitfield::<M>();
+        if (x >= M::SINGLE_MAP_WIDTH) == is_left_player {
+            (M::Bits::ZERO, bitfield)
         } else {
-            (bitfield, 0)
+            (bitfield, M::Bits::ZERO)
         }
     }
 
     pub fn x(self) -> u8 {
-        self.index % SINGLE_MAP_WIDTH
+        self.x
     }
 
     pub fn y(self) -> u8 {
-        self.index / SINGLE_MAP_WIDTH
+        self.y
     }
 }
 
@@ -46,13 +59,16 @@ impl Point {
      * # Bitfields
      * 
      * 0,0 is the top left point.
-     * >> (towards 0) moves bits towards the player that owns that side
-     * << (towards max) moves bits towards the opponent
+     * `>>` (towards 0) moves bits towards the player that owns that side
+     * `<<` (towards max) moves bits towards the opponent
      * This involves mirroring the x dimension for the opponent's side
      */
+    pub fn bit_index<M: MapSize>(self) -> u8 {
+        self.y * M::SINGLE_MAP_WIDTH + self.x
+    }
 
-    pub fn to_either_bitfield(self) -> u64 {
-        1u64 << self.index
+    pub fn to_either_bitfield<M: MapSize>(self) -> M::Bits {
+        M::Bits::bit(self.bit_index::<M>())
     }
 }
 
@@ -66,6 +82,6 @@ impl PartialOrd for Point {
 }
 impl Ord for Point {
     fn cmp(&self, other: &Point) -> Ordering {
-        self.index.cmp(&other.index)
+        (self.y, self.x).cmp(&(other.y, other.x))
     }
 }
@@ -0,0 +1,103 @@
+use engine::command::{Command, BuildingType};
+use engine::geometry::Point;
+use engine::bitboard::{Bitboard, MapSize};
+use engine::constants::*;
+
+use arrayvec::ArrayVec;
+
+/// Energy towers aren't worth building once the player makes more than
+/// this much energy a round...
+pub const ENERGY_PRODUCTION_CUTOFF: u16 = 50;
+/// ...and has more than this much saved up.
+pub const ENERGY_STORAGE_CUTOFF: u16 = 120;
+
+/**
+ * The commands that a player can play in a round. Every building that
+ * can be built can go on any free cell, so the commands are kept as
+ * the building types and a bitboard of cells, and only listed out when
+ * they're needed.
+ *
+ * Doing nothing is always allowed.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LegalCommands<M: MapSize> {
+    /// The buildings that can be built, in the same order as
+    /// `BuildingType::all`. This is empty if there's nowhere to build.
+    pub buildings: ArrayVec<[BuildingType; NUMBER_OF_BUILDING_TYPES]>,
+    pub build_cells: M::Bits,
+    pub deconstruct_cells: M::Bits,
+    pub iron_curtain: bool
+}
+
+impl<M: MapSize> LegalCommands<M> {
+    pub fn can_build(&self, building: BuildingType) -> bool {
+        self.buildings.contains(&building)
+    }
+
+    pub fn build_cell_count(&self) -> usize {
+        self.build_cells.count_ones() as usize
+    }
+
+    pub fn build_cell(&self, i: usize) -> Point {
+        Point::from_bit_index::<M>(self.build_cells.select(i as u32))
+    }
+
+    pub fn deconstruct_cell_count(&self) -> usize {
+        self.deconstruct_cells.count_ones() as usize
+    }
+
+    pub fn deconstruct_cell(&self, i: usize) -> Point {
+        Point::from_bit_index::<M>(self.deconstruct_cells.select(i as u32))
+    }
+
+    /// The number of commands, including doing nothing.
+    pub fn count(&self) -> usize {
+        1 + self.iron_curtain as usize
+            + self.build_cell_count() * self.buildings.len()
+            + self.deconstruct_cell_count()
+    }
+
+    pub fn contains(&self, command: Command) -> bool {
+        let on = |cells: M::Bits, p: Point| {
+            p.x() < M::SINGLE_MAP_WIDTH && p.y() < M::MAP_HEIGHT &&
+                cells & p.to_either_bitfield::<M>() != M::Bits::ZERO
+        };
+        match command {
+            Command::Nothing => true,
+            Command::IronCurtain => self.iron_curtain,
+            Command::Build(p, b) => self.can_build(b) && on(self.build_cells, p),
+            Command::Deconstruct(p) => on(self.deconstruct_cells, p)
+        }
+    }
+
+    /// Every building on every free cell, a cell at a time.
+    pub fn builds<'a>(&'a self) -> impl Iterator<Item = Command> + 'a {
+        cells::<M>(self.build_cells)
+            .flat_map(move |p| self.buildings.iter().map(move |&b| Command::Build(p, b)))
+    }
+
+    pub fn deconstructs(&self) -> impl Iterator<Item = Command> {
+        cells::<M>(self.deconstruct_cells).map(Command::Deconstruct)
+    }
+
+    /// Doing nothing, the iron curtain, building and then deconstructing.
+    pub fn iter<'a>(&'a self) -> impl Iterator<Item = Command> + 'a {
+        let iron_curtain = if self.iron_curtain { Some(Command::IronCurtain) } else { None };
+        Some(Command::Nothing).into_iter()
+            .chain(iron_curtain)
+            .chain(self.builds())
+            .chain(self.deconstructs())
+    }
+}
+
+/// The cells in a bitboard, from the lowest bit up.
+fn cells<M: MapSize>(mut bits: M::Bits) -> impl Iterator<Item = Point> {
+    ::std::iter::from_fn(move || {
+        if bits == M::Bits::ZERO {
+            return None;
+        }
+        let index = bits.trailing_zeros() as u8;
+        bits &= !M::Bits::bit(index);
+        Some(Point::from_bit_index::<M>(index))
+    })
+}
@@ -1,5 +1,11 @@
 pub mod command;
 pub mod geometry;
+pub mod bitboard;
 pub mod bitwise_engine;
+pub mod legal_commands;
+pub mod cell;
+pub mod display;
 pub mod constants;
+pub mod config;
 pub mod status;
+pub mod zobrist;
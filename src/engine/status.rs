@@ -6,3 +6,14 @@ pub enum GameStatus {
     Draw
 }
 
+impl GameStatus {
+    /// The same result from the opponent's side.
+    pub fn swapped(self) -> GameStatus {
+        match self {
+            GameStatus::PlayerWon => GameStatus::OpponentWon,
+            GameStatus::OpponentWon => GameStatus::PlayerWon,
+            status => status
+        }
+    }
+}
+
@@ -0,0 +1,193 @@
+use engine::bitboard::{Bitboard, MapSize, MAX_MAP_POSITIONS, MAX_MISSILE_TIERS};
+use engine::bitwise_engine::{UnconstructedBuilding, TeslaCooldown};
+use engine::constants::*;
+
+pub const PLAYER: usize = 0;
+pub const OPPONENT: usize = 1;
+
+const BUILDING_KEYS: usize = 0;
+const ENERGY_TOWER_KEYS: usize = BUILDING_KEYS + DEFENCE_HEALTH;
+const MISSILE_TOWER_KEYS: usize = ENERGY_TOWER_KEYS + 1;
+const OWN_HALF_MISSILE_KEYS: usize = MISSILE_TOWER_KEYS + MISSILE_COOLDOWN_STATES;
+const OTHER_HALF_MISSILE_KEYS: usize = OWN_HALF_MISSILE_KEYS + MAX_MISSILE_TIERS;
+const CELL_KEYS_PER_PLAYER: usize = OTHER_HALF_MISSILE_KEYS + MAX_MISSILE_TIERS;
+
+static CELL_KEYS: [[u64; MAX_MAP_POSITIONS]; 2 * CELL_KEYS_PER_PLAYER] = cell_keys();
+
+// Keys for everything other than cells are made by mixing the value
+// with one of these tags in the high bits.
+const ROUND_TAG: u64 = 1;
+const ENERGY_TAG: u64 = 2;
+const HEALTH_TAG: u64 = 4;
+const IRON_CURTAIN_TAG: u64 = 6;
+const UNCONSTRUCTED_TAG: u64 = 8;
+const TESLA_TAG: u64 = 10;
+
+const fn splitmix64(x: u64) -> u64 {
+    let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+const fn cell_keys() -> [[u64; MAX_MAP_POSITIONS]; 2 * CELL_KEYS_PER_PLAYER] {
+    let mut keys = [[0; MAX_MAP_POSITIONS]; 2 * CELL_KEYS_PER_PLAYER];
+    let mut i = 0;
+    while i < keys.len() {
+        let mut j = 0;
+        while j < MAX_MAP_POSITIONS {
+            keys[i][j] = splitmix64((i * MAX_MAP_POSITIONS + j) as u64);
+            j += 1;
+        }
+        i += 1;
+    }
+    keys
+}
+
+/// The value has to fit in the low 40 bits.
+fn value_key(tag: u64, side: usize, value: u64) -> u64 {
+    splitmix64(((tag + side as u64) << 40) | value)
+}
+
+fn cells<B: Bitboard>(side: usize, keys: usize, mut bits: B) -> u64 {
+    let keys = &CELL_KEYS[side * CELL_KEYS_PER_PLAYER + keys];
+    let mut hash = 0;
+    while bits != B::ZERO {
+        let index = bits.trailing_zeros();
+        hash ^= keys[index as usize];
+        bits &= !B::bit(index as u8);
+    }
+    hash
+}
+
+pub fn round(round: u16) -> u64 {
+    value_key(ROUND_TAG, 0, u64::from(round))
+}
+
+pub fn energy(side: usize, energy: u16) -> u64 {
+    value_key(ENERGY_TAG, side, u64::from(energy))
+}
+
+pub fn health(side: usize, health: u8) -> u64 {
+    value_key(HEALTH_TAG, side, u64::from(health))
+}
+
+pub fn iron_curtain(side: usize, available: bool, remaining: u8) -> u64 {
+    value_key(IRON_CURTAIN_TAG, side, (u64::from(available) << 8) | u64::from(remaining))
+}
+
+// The cells' parts of the hash are worked out as the change from one
+// board to another. Each cell has its own key, so only the cells that
+// changed need hashing. The hash of a board on its own is the change
+// from an empty one.
+
+pub fn buildings<M: MapSize>(side: usize, buildings: &[M::Bits; DEFENCE_HEALTH]) -> u64 {
+    buildings_change::<M>(side, &[M::Bits::ZERO; DEFENCE_HEALTH], buildings)
+}
+
+pub fn buildings_change<M: MapSize>(side: usize, before: &[M::Bits; DEFENCE_HEALTH], after: &[M::Bits; DEFENCE_HEALTH]) -> u64 {
+    before.iter().zip(after.iter()).enumerate()
+        .fold(0, |hash, (tier, (&before, &after))| hash ^ cells(side, BUILDING_KEYS + tier, before ^ after))
+}
+
+pub fn energy_towers<M: MapSize>(side: usize, energy_towers: M::Bits) -> u64 {
+    energy_towers_change::<M>(side, M::Bits::ZERO, energy_towers)
+}
+
+pub fn energy_towers_change<M: MapSize>(side: usize, before: M::Bits, after: M::Bits) -> u64 {
+    cells(side, ENERGY_TOWER_KEYS, before ^ after)
+}
+
+pub fn missile_towers<M: MapSize>(side: usize, missile_towers: &[M::Bits; MISSILE_COOLDOWN_STATES], firing_tower: usize) -> u64 {
+    missile_towers_change::<M>(side, &[M::Bits::ZERO; MISSILE_COOLDOWN_STATES], 0, missile_towers, firing_tower)
+}
+
+/**
+ * The towers are hashed by how long they have until they fire, rather
+ * than where they are in the rotating list of cooldowns.
+ */
+pub fn missile_towers_change<M: MapSize>(side: usize, before: &[M::Bits; MISSILE_COOLDOWN_STATES], before_firing_tower: usize, after: &[M::Bits; MISSILE_COOLDOWN_STATES], after_firing_tower: usize) -> u64 {
+    (0..MISSILE_COOLDOWN_STATES).fold(0, |hash, wait| {
+        let before = before[(before_firing_tower + wait) % MISSILE_COOLDOWN_STATES];
+        let after = after[(after_firing_tower + wait) % MISSILE_COOLDOWN_STATES];
+        hash ^ cells(side, MISSILE_TOWER_KEYS + wait, before ^ after)
+    })
+}
+
+pub fn missiles<M: MapSize>(side: usize, missiles: &M::Missiles) -> u64 {
+    missiles_change::<M>(side, &M::Missiles::default(), missiles)
+}
+
+/**
+ * Only the number of missiles in each cell matters, not which of the
+ * bitfields they're in, so they're counted into layers first. The
+ * first layer has every cell with at least one missile, the second has
+ * every cell with at least two, and so on.
+ */
+pub fn missiles_change<M: MapSize>(side: usize, before: &M::Missiles, after: &M::Missiles) -> u64 {
+    let (before_own_half, before_other_half) = missile_layers::<M>(before);
+    let (after_own_half, after_other_half) = missile_layers::<M>(after);
+
+    let mut hash = 0;
+    for layer in 0..MAX_MISSILE_TIERS {
+        hash ^= cells(side, OWN_HALF_MISSILE_KEYS + layer, before_own_half[layer] ^ after_own_half[layer]);
+        hash ^= cells(side, OTHER_HALF_MISSILE_KEYS + layer, before_other_half[layer] ^ after_other_half[layer]);
+    }
+    hash
+}
+
+fn missile_layers<M: MapSize>(missiles: &M::Missiles) -> ([M::Bits; MAX_MISSILE_TIERS], [M::Bits; MAX_MISSILE_TIERS]) {
+    let mut own_half = [M::Bits::ZERO; MAX_MISSILE_TIERS];
+    let mut other_half = [M::Bits::ZERO; MAX_MISSILE_TIERS];
+    for &(own, other) in missiles.as_ref() {
+        add_to_layers(&mut own_half, own);
+        add_to_layers(&mut other_half, other);
+    }
+    (own_half, other_half)
+}
+
+fn add_to_layers<B: Bitboard>(layers: &mut [B], mut missiles: B) {
+    for layer in layers.iter_mut() {
+        let carry = *layer & missiles;
+        *layer |= missiles;
+        missiles = carry;
+    }
+}
+
+pub fn unconstructed(side: usize, unconstructed: &[UnconstructedBuilding]) -> u64 {
+    unconstructed.iter().fold(0, |hash, building| {
+        let value = (u64::from(building.pos.x()) << 24)
+            | (u64::from(building.pos.y()) << 16)
+            | (u64::from(building.construction_time_left) << 8)
+            | building.building_type as u64;
+        hash ^ value_key(UNCONSTRUCTED_TAG, side, value)
+    })
+}
+
+/**
+ * A tesla's age doesn't change how it plays, since the teslas fire in
+ * order of position. It's left out so that teslas built in different
+ * rounds don't keep otherwise equal states apart. Being in order of
+ * position, teslas in the same places are always in the same order,
+ * so the order doesn't need hashing either.
+ */
+pub fn teslas(side: usize, teslas: &[TeslaCooldown]) -> u64 {
+    teslas.iter().fold(0, |hash, tesla| {
+        let value = (u64::from(tesla.pos.x()) << 16)
+            | (u64::from(tesla.pos.y()) << 8)
+            | u64::from(tesla.cooldown);
+        hash ^ value_key(TESLA_TAG, side, value)
+    })
+}
+
+/**
+ * The change to a hash when part of the state goes from `before` to
+ * `after`, which is nothing if it stayed the same.
+ */
+pub fn change<T: PartialEq + ?Sized, F: Fn(&T) -> u64>(before: &T, after: &T, hash: F) -> u64 {
+    if before == after {
+        0
+    } else {
+        hash(before) ^ hash(after)
+    }
+}
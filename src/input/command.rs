@@ -0,0 +1,78 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::error::Error;
+
+use engine::command::{Command, BuildingType};
+use engine::geometry::Point;
+use engine::bitboard::MapSize;
+
+/**
+ * Reads a command in the format that bots write to `command.txt`,
+ * with coordinates on the full map.
+ */
+pub fn read_command_from_file<M: MapSize>(filename: &str) -> Result<Command, Box<dyn Error>> {
+    let mut file = File::open(filename)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    parse_command::<M>(&content)
+}
+
+/**
+ * Reads a command written by a bot that sees itself as player A, so
+ * that it may only refer to cells on the left half of the map.
+ */
+pub fn read_own_command_from_file<M: MapSize>(filename: &str) -> Result<Command, Box<dyn Error>> {
+    let mut file = File::open(filename)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    parse_command_within::<M>(&content, M::SINGLE_MAP_WIDTH)
+}
+
+pub fn parse_command<M: MapSize>(content: &str) -> Result<Command, Box<dyn Error>> {
+    parse_command_within::<M>(content, M::FULL_MAP_WIDTH)
+}
+
+fn parse_command_within<M: MapSize>(content: &str, map_width: u8) -> Result<Command, Box<dyn Error>> {
+    let content = content.trim();
+    if content.is_empty() || content == "No Command" {
+        return Ok(Command::Nothing);
+    }
+
+    let components = content.split(',')
+        .map(|c| c.trim().parse::<u8>())
+        .collect::<Result<Vec<_>, _>>()?;
+    if components.len() != 3 {
+        return Err(format!("Expected 'x,y,action' but found '{}'", content).into());
+    }
+    let (x, y, action_type) = (components[0], components[1], components[2]);
+    if x >= map_width || y >= M::MAP_HEIGHT {
+        return Err(format!("Command point {},{} is out of bounds", x, y).into());
+    }
+
+    let point = Point::from_map_coordinates::<M>(x, y);
+    match action_type {
+        5 => Ok(Command::IronCurtain),
+        3 => Ok(Command::Deconstruct(point)),
+        _ => BuildingType::from_u8(action_type)
+            .map(|building| Command::Build(point, building))
+            .ok_or_else(|| format!("Unknown action type {}", action_type).into())
+    }
+}
+
+/**
+ * Formats a command the way the game engine records it in replays.
+ * Each bot sees itself as player A, so the coordinates are on that
+ * player's own side of the map.
+ */
+pub fn format_replay_command(command: Command) -> String {
+    match command {
+        Command::Nothing => "No Command".to_string(),
+        _ => command.to_string()
+    }
+}
+
+pub fn write_replay_command_to_file(command: Command, filename: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = File::create(filename)?;
+    write!(file, "{}", format_replay_command(command))?;
+    Ok(())
+}
@@ -6,97 +6,293 @@ use std::error::Error;
 use engine;
 use engine::command;
 use engine::bitwise_engine;
+use engine::bitboard::{Bitboard, MapSize, Map16x8};
+use engine::config::GameConfig;
 use engine::constants::*;
+use strategy::config::BotConfig;
+use strategy::decision_log::DecisionRecord;
 
-pub fn read_bitwise_state_from_file(filename: &str) -> Result<bitwise_engine::BitwiseGameState, Box<Error>> {
+pub fn read_bitwise_state_from_file(filename: &str) -> Result<bitwise_engine::BitwiseGameState, Box<dyn Error>> {
+    read_state_from_file::<Map16x8>(filename)
+}
+
+pub fn write_bitwise_state_to_file(state: &bitwise_engine::BitwiseGameState, filename: &str) -> Result<(), Box<dyn Error>> {
+    write_state_to_file::<Map16x8>(state, filename)
+}
+
+/**
+ * Writes the state in the same format that the game engine gives to
+ * bots, from the point of view of `state.player` as player A.
+ */
+pub fn write_state_to_file<M: MapSize>(state: &bitwise_engine::BitwiseGameState<M>, filename: &str) -> Result<(), Box<dyn Error>> {
+    let file = File::create(filename)?;
+    write_state(file, state)
+}
+
+fn write_state<M: MapSize, W: Write>(writer: W, state: &bitwise_engine::BitwiseGameState<M>) -> Result<(), Box<dyn Error>> {
+    let json_state = State::from_bitwise_engine(state);
+    serde_json::to_writer(writer, &json_state)?;
+    Ok(())
+}
+
+pub fn state_to_string<M: MapSize>(state: &bitwise_engine::BitwiseGameState<M>) -> Result<String, Box<dyn Error>> {
+    let mut content = Vec::new();
+    write_state(&mut content, state)?;
+    Ok(String::from_utf8(content)?)
+}
+
+pub fn read_state_from_file<M: MapSize>(filename: &str) -> Result<bitwise_engine::BitwiseGameState<M>, Box<dyn Error>> {
     let mut file = File::open(filename)?;
     let mut content = String::new();
     file.read_to_string(&mut content)?;
-    let state: State = serde_json::from_str(content.as_ref())?;
+    state_from_str(&content)
+}
+
+pub fn state_from_str<M: MapSize>(content: &str) -> Result<bitwise_engine::BitwiseGameState<M>, Box<dyn Error>> {
+    let state: State = serde_json::from_str(content)?;
 
-    let engine_state = state.to_bitwise_engine();
+    let engine_state = state.to_bitwise_engine()?;
     Ok(engine_state)
 }
 
+pub fn read_bot_config_from_file(filename: &str) -> Result<BotConfig, Box<dyn Error>> {
+    let mut file = File::open(filename)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let config = serde_json::from_str(content.as_ref())?;
+    Ok(config)
+}
+
+pub fn read_decision_record_from_file(filename: &str) -> Result<DecisionRecord, Box<dyn Error>> {
+    let mut file = File::open(filename)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let record = serde_json::from_str(content.as_ref())?;
+    Ok(record)
+}
+
+pub fn write_decision_record_to_file(record: &DecisionRecord, filename: &str) -> Result<(), Box<dyn Error>> {
+    let file = File::create(filename)?;
+    serde_json::to_writer(file, record)?;
+    Ok(())
+}
+
+/**
+ * Reads just the map size, so that the state can be read with a
+ * matching `MapSize`.
+ */
+pub fn read_map_size_from_file(filename: &str) -> Result<(u8, u8), Box<dyn Error>> {
+    let mut file = File::open(filename)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let state: MapSizeOnlyState = serde_json::from_str(content.as_ref())?;
+
+    let map_width = state.game_details.map_width.unwrap_or(Map16x8::FULL_MAP_WIDTH);
+    let map_height = state.game_details.map_height.unwrap_or(Map16x8::MAP_HEIGHT);
+    Ok((map_width, map_height))
+}
+
+/**
+ * Reads which player the state was given to. Bots always see their own
+ * half of the map on the left, so this is the owner of the cells in
+ * the first column.
+ */
+pub fn read_viewing_player_type_from_file(filename: &str) -> Result<char, Box<dyn Error>> {
+    let mut file = File::open(filename)?;
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let state: CellOwnerOnlyState = serde_json::from_str(content.as_ref())?;
+
+    state.game_map.iter()
+        .flat_map(|row| row.iter())
+        .find(|cell| cell.x == 0)
+        .and_then(|cell| cell.cell_owner)
+        .ok_or_else(|| "No cell owner in the first column of the map".into())
+}
+
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct MapSizeOnlyState {
+    game_details: MapSizeOnlyGameDetails
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MapSizeOnlyGameDetails {
+    map_width: Option<u8>,
+    map_height: Option<u8>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CellOwnerOnlyState {
+    game_map: Vec<Vec<CellOwnerOnlyCell>>
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CellOwnerOnlyCell {
+    x: u8,
+    cell_owner: Option<char>
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct State {
     game_details: GameDetails,
     players: Vec<Player>,
     game_map: Vec<Vec<GameCell>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GameDetails {
-    round: u16
+    round: u16,
+    max_rounds: Option<u16>,
+    map_width: Option<u8>,
+    map_height: Option<u8>,
+    round_income_energy: Option<u16>,
+    building_prices: Option<BuildingPrices>,
+    buildings_stats: Option<BuildingsStats>,
+    iron_curtain_stats: Option<IronCurtainStats>,
+    // The game engine doesn't write these, but the arena does when it
+    // plays with rules other than the defaults
+    tesla_firing_energy: Option<u16>,
+    tesla_range: Option<u8>,
+    deconstruct_energy: Option<u16>
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct BuildingPrices {
+    defense: u16,
+    attack: u16,
+    energy: u16,
+    tesla: u16
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+struct BuildingsStats {
+    defense: BuildingStats,
+    attack: BuildingStats,
+    energy: BuildingStats,
+    tesla: BuildingStats
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BuildingStats {
+    health: u8,
+    construction_time: u8,
+    price: u16,
+    weapon_damage: u8,
+    weapon_speed: u8,
+    weapon_cooldown_period: u8,
+    energy_generated_per_turn: u16
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IronCurtainStats {
+    active_rounds: u8,
+    reset_period: u16,
+    price: u16
+}
+
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct Player {
     player_type: char,
     energy: u16,
     health: u8,
     iron_curtain_available: bool,
-    active_iron_curtain_lifetime: i16
+    active_iron_curtain_lifetime: i16,
+    // Score isn't simulated, so these are written as 0
+    #[serde(default)]
+    hits_taken: u16,
+    #[serde(default)]
+    score: u32
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct GameCell {
     x: u8,
     y: u8,
     buildings: Vec<BuildingState>,
     missiles: Vec<MissileState>,
+    #[serde(default)]
+    cell_owner: char
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct BuildingState {
     health: u8,
     construction_time_left: i16,
+    #[serde(default)]
+    price: u16,
+    #[serde(default)]
+    weapon_damage: u8,
+    #[serde(default)]
+    weapon_speed: u8,
     weapon_cooldown_time_left: u8,
+    #[serde(default)]
+    weapon_cooldown_period: u8,
+    #[serde(default)]
+    energy_generated_per_turn: u16,
     building_type: String,
     x: u8,
     y: u8,
     player_type: char
 }
 
-#[derive(Deserialize)]
+#[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct MissileState {
+    #[serde(default)]
+    damage: u8,
+    #[serde(default)]
+    speed: u8,
+    #[serde(default)]
+    x: u8,
+    #[serde(default)]
+    y: u8,
     player_type: char
 }
 
 
 impl State {
-    fn to_bitwise_engine(&self) -> bitwise_engine::BitwiseGameState {
-        let mut player = bitwise_engine::Player::empty();
-        let mut opponent = bitwise_engine::Player::empty();
+    fn to_bitwise_engine<M: MapSize>(&self) -> Result<bitwise_engine::BitwiseGameState<M>, Box<dyn Error>> {
+        let config = self.game_details.to_config::<M>()?;
+        
+        let mut player = bitwise_engine::Player::<M>::empty();
+        let mut opponent = bitwise_engine::Player::<M>::empty();
 
         self.player().map_onto_engine(&mut player);
         self.opponent().map_onto_engine(&mut opponent);
         
         for row in &self.game_map {
             for cell in row {
-                let point = engine::geometry::Point::new(cell.x, cell.y);
+                let point = engine::geometry::Point::from_map_coordinates::<M>(cell.x, cell.y);
                 for building in &cell.buildings {
                     let building_type = building.convert_building_type();
                     
-                    let mut bitwise_buildings = if building.player_type == 'A' {
+                    let bitwise_buildings = if building.player_type == 'A' {
                         &mut player
                     } else {
                         &mut opponent
                     };
-                    let bitfield = point.to_either_bitfield();
+                    let bitfield = point.to_either_bitfield::<M>();
 
                     bitwise_buildings.occupied |= bitfield;
                     if building.construction_time_left >= 0 {
-                        bitwise_buildings.unconstructed.push(building.to_bitwise_engine_unconstructed());
+                        bitwise_buildings.unconstructed.try_push(building.to_bitwise_engine_unconstructed::<M>())
+                            .map_err(|_| format!("More than {} buildings under construction", MAX_CONCURRENT_CONSTRUCTION))?;
                     } else {
                         for health_tier in 0..DEFENCE_HEALTH {
-                            if building.health > health_tier as u8 * MISSILE_DAMAGE {
+                            if building.health > health_tier as u8 * config.missile_damage {
                                 bitwise_buildings.buildings[health_tier] |= bitfield;
                             }
                         }
@@ -111,23 +307,23 @@ impl State {
                             }
                         }
                         else if building_type == command::BuildingType::Tesla {
-                            bitwise_buildings.tesla_cooldowns.push(bitwise_engine::TeslaCooldown { 
+                            bitwise_buildings.add_tesla(bitwise_engine::TeslaCooldown {
                                 pos: point,
                                 cooldown: building.weapon_cooldown_time_left,
-                                age: building.construction_time_left.abs() as u16
-                            });
+                                age: building.construction_time_left.unsigned_abs()
+                            }).map_err(|_| format!("More than {} teslas", TESLA_MAX))?;
                         }
                     }
                 }
                 for missile in &cell.missiles {
-                    let (mut left, mut right) = engine::geometry::Point::new_double_bitfield(cell.x, cell.y, missile.player_type == 'A');
-                    let mut bitwise_buildings = if missile.player_type == 'A' {
+                    let (mut left, mut right) = engine::geometry::Point::new_double_bitfield::<M>(cell.x, cell.y, missile.player_type == 'A');
+                    let bitwise_buildings = if missile.player_type == 'A' {
                         &mut player
                     } else {
                         &mut opponent
                     };
 
-                    for mut tier in &mut bitwise_buildings.missiles {
+                    for tier in bitwise_buildings.missiles.as_mut() {
                         let setting = (!tier.0 & left, !tier.1 & right);
                         tier.0 |= setting.0;
                         tier.1 |= setting.1;
@@ -138,10 +334,11 @@ impl State {
             }
         }
             
-        bitwise_engine::BitwiseGameState::new(
+        Ok(bitwise_engine::BitwiseGameState::new(
             player, opponent,
-            self.game_details.round
-        )
+            self.game_details.round,
+            config
+        ))
     }
 
     fn player(&self) -> &Player {
@@ -157,10 +354,286 @@ impl State {
     }
 }
 
+impl State {
+    fn from_bitwise_engine<M: MapSize>(state: &bitwise_engine::BitwiseGameState<M>) -> State {
+        let buildings_stats = BuildingsStats::from_config(&state.config);
+        let mut game_map = Vec::with_capacity(M::MAP_HEIGHT as usize);
+        for y in 0..M::MAP_HEIGHT {
+            let mut row = Vec::with_capacity(M::FULL_MAP_WIDTH as usize);
+            for x in 0..M::FULL_MAP_WIDTH {
+                let point = engine::geometry::Point::from_map_coordinates::<M>(x, y);
+                let is_left_half = x < M::SINGLE_MAP_WIDTH;
+                let (owner_type, owner, visitor_type, visitor) = if is_left_half {
+                    ('A', &state.player, 'B', &state.opponent)
+                } else {
+                    ('B', &state.opponent, 'A', &state.player)
+                };
+
+                let buildings = BuildingState::from_bitwise_engine(owner, point, x, owner_type, &buildings_stats)
+                    .into_iter()
+                    .collect();
+
+                let bitfield = point.to_either_bitfield::<M>();
+                let missile = |player_type| MissileState {
+                    damage: state.config.missile_damage,
+                    speed: MISSILE_SPEED as u8,
+                    x, y,
+                    player_type
+                };
+                let mut missiles = Vec::new();
+                for &(own_side, _) in owner.missiles.as_ref() {
+                    if own_side & bitfield != M::Bits::ZERO {
+                        missiles.push(missile(owner_type));
+                    }
+                }
+                for &(_, opponent_side) in visitor.missiles.as_ref() {
+                    if opponent_side & bitfield != M::Bits::ZERO {
+                        missiles.push(missile(visitor_type));
+                    }
+                }
+
+                row.push(GameCell {
+                    x, y,
+                    buildings,
+                    missiles,
+                    cell_owner: owner_type
+                });
+            }
+            game_map.push(row);
+        }
+
+        State {
+            game_details: GameDetails::from_config::<M>(state.round, &state.config),
+            players: vec!(
+                Player::from_bitwise_engine('A', &state.player),
+                Player::from_bitwise_engine('B', &state.opponent)
+            ),
+            game_map
+        }
+    }
+}
+
+impl GameDetails {
+    fn from_config<M: MapSize>(round: u16, config: &GameConfig) -> GameDetails {
+        GameDetails {
+            round,
+            max_rounds: Some(config.max_rounds),
+            map_width: Some(M::FULL_MAP_WIDTH),
+            map_height: Some(M::MAP_HEIGHT),
+            round_income_energy: Some(config.energy_generated_base),
+            building_prices: Some(BuildingPrices {
+                defense: config.defence_price,
+                attack: config.missile_price,
+                energy: config.energy_price,
+                tesla: config.tesla_price
+            }),
+            buildings_stats: Some(BuildingsStats::from_config(config)),
+            iron_curtain_stats: Some(IronCurtainStats {
+                active_rounds: config.iron_curtain_duration,
+                reset_period: config.iron_curtain_unlock_interval,
+                price: config.iron_curtain_price
+            }),
+            tesla_firing_energy: Some(config.tesla_firing_energy),
+            tesla_range: Some(config.tesla_range),
+            deconstruct_energy: Some(config.deconstruct_energy)
+        }
+    }
+}
+
+impl BuildingsStats {
+    fn from_config(config: &GameConfig) -> BuildingsStats {
+        let building = |health, construction_time, price| BuildingStats {
+            health, construction_time, price,
+            weapon_damage: 0,
+            weapon_speed: 0,
+            weapon_cooldown_period: 0,
+            energy_generated_per_turn: 0
+        };
+
+        BuildingsStats {
+            defense: building(config.missile_damage * DEFENCE_HEALTH as u8, config.defence_construction_time, config.defence_price),
+            attack: BuildingStats {
+                weapon_damage: config.missile_damage,
+                weapon_speed: MISSILE_SPEED as u8,
+                weapon_cooldown_period: MISSILE_COOLDOWN as u8,
+                ..building(config.missile_damage, config.missile_construction_time, config.missile_price)
+            },
+            energy: BuildingStats {
+                energy_generated_per_turn: config.energy_generated_tower,
+                ..building(config.missile_damage, config.energy_construction_time, config.energy_price)
+            },
+            tesla: BuildingStats {
+                weapon_damage: config.tesla_damage,
+                weapon_cooldown_period: config.tesla_cooldown,
+                ..building(config.missile_damage, config.tesla_construction_time, config.tesla_price)
+            }
+        }
+    }
+}
+
+impl BuildingsStats {
+    fn for_building(&self, building_type: command::BuildingType) -> &BuildingStats {
+        match building_type {
+            command::BuildingType::Defence => &self.defense,
+            command::BuildingType::Attack => &self.attack,
+            command::BuildingType::Energy => &self.energy,
+            command::BuildingType::Tesla => &self.tesla,
+        }
+    }
+}
+
+impl BuildingState {
+    fn from_bitwise_engine<M: MapSize>(player: &bitwise_engine::Player<M>, point: engine::geometry::Point, x: u8, player_type: char, stats: &BuildingsStats) -> Option<BuildingState> {
+        let cell_building = player.building_at(point)?;
+        let building_stats = stats.for_building(cell_building.building_type);
+
+        let (health, construction_time_left) = if cell_building.is_under_construction() {
+            (building_stats.health, i16::from(cell_building.construction_time_left))
+        } else if cell_building.building_type == command::BuildingType::Tesla {
+            // The age is read back from how long ago construction finished
+            (cell_building.health_tiers * stats.attack.weapon_damage, -(cell_building.age.max(1) as i16))
+        } else {
+            // Each health tier is one missile hit
+            (cell_building.health_tiers * stats.attack.weapon_damage, -1)
+        };
+
+        Some(BuildingState {
+            health,
+            construction_time_left,
+            price: building_stats.price,
+            weapon_damage: building_stats.weapon_damage,
+            weapon_speed: building_stats.weapon_speed,
+            weapon_cooldown_time_left: cell_building.weapon_cooldown,
+            weapon_cooldown_period: building_stats.weapon_cooldown_period,
+            energy_generated_per_turn: building_stats.energy_generated_per_turn,
+            building_type: building_type_name(cell_building.building_type).to_string(),
+            x,
+            y: point.y(),
+            player_type
+        })
+    }
+}
+
+fn building_type_name(building_type: command::BuildingType) -> &'static str {
+    match building_type {
+        command::BuildingType::Attack => "ATTACK",
+        command::BuildingType::Energy => "ENERGY",
+        command::BuildingType::Tesla => "TESLA",
+        command::BuildingType::Defence => "DEFENSE",
+    }
+}
+
+impl Player {
+    fn from_bitwise_engine<M: MapSize>(player_type: char, engine_player: &bitwise_engine::Player<M>) -> Player {
+        Player {
+            player_type,
+            energy: engine_player.energy,
+            health: engine_player.health,
+            iron_curtain_available: engine_player.iron_curtain_available,
+            active_iron_curtain_lifetime: i16::from(engine_player.iron_curtain_remaining),
+            hits_taken: 0,
+            score: 0
+        }
+    }
+}
+
+impl GameDetails {
+    fn to_config<M: MapSize>(&self) -> Result<GameConfig, Box<dyn Error>> {
+        let mut config = GameConfig::default();
+
+        let map_width = self.map_width.unwrap_or(Map16x8::FULL_MAP_WIDTH);
+        let map_height = self.map_height.unwrap_or(Map16x8::MAP_HEIGHT);
+        if map_width != M::FULL_MAP_WIDTH || map_height != M::MAP_HEIGHT {
+            return Err(format!("Unsupported map size {}x{}", map_width, map_height).into());
+        }
+        
+        if let Some(max_rounds) = self.max_rounds {
+            config.max_rounds = max_rounds;
+        }
+        if let Some(round_income_energy) = self.round_income_energy {
+            config.energy_generated_base = round_income_energy;
+        }
+        if let Some(ref stats) = self.buildings_stats {
+            stats.map_onto_config(&mut config)?;
+        } else if let Some(ref prices) = self.building_prices {
+            prices.map_onto_config(&mut config);
+        }
+        if let Some(ref iron_curtain) = self.iron_curtain_stats {
+            config.iron_curtain_price = iron_curtain.price;
+            config.iron_curtain_unlock_interval = iron_curtain.reset_period;
+            config.iron_curtain_duration = iron_curtain.active_rounds;
+        }
+        if let Some(tesla_firing_energy) = self.tesla_firing_energy {
+            config.tesla_firing_energy = tesla_firing_energy;
+        }
+        if let Some(tesla_range) = self.tesla_range {
+            config.tesla_range = tesla_range;
+        }
+        if let Some(deconstruct_energy) = self.deconstruct_energy {
+            config.deconstruct_energy = deconstruct_energy;
+        }
+        
+        Ok(config)
+    }
+}
+
+impl BuildingPrices {
+    /// Only for states without `buildingsStats`, which has the prices too.
+    fn map_onto_config(&self, config: &mut GameConfig) {
+        config.defence_price = self.defense;
+        config.missile_price = self.attack;
+        config.energy_price = self.energy;
+        config.tesla_price = self.tesla;
+    }
+}
+
+impl BuildingsStats {
+    fn map_onto_config(&self, config: &mut GameConfig) -> Result<(), Box<dyn Error>> {
+        let missile_damage = self.attack.weapon_damage;
+        if missile_damage == 0 || usize::from(self.defense.health / missile_damage) != DEFENCE_HEALTH {
+            return Err(format!("Unsupported defence health {} for missile damage {}", self.defense.health, missile_damage).into());
+        }
+        if [&self.attack, &self.energy, &self.tesla].iter().any(|b| b.health > missile_damage) {
+            return Err("Unsupported health for non-defence building".into());
+        }
+        if usize::from(self.attack.weapon_cooldown_period) != MISSILE_COOLDOWN {
+            return Err(format!("Unsupported missile cooldown {}", self.attack.weapon_cooldown_period).into());
+        }
+        if usize::from(self.attack.weapon_speed) != MISSILE_SPEED {
+            return Err(format!("Unsupported missile speed {}", self.attack.weapon_speed).into());
+        }
+
+        config.missile_damage = missile_damage;
+        config.missile_price = self.attack.price;
+        config.missile_construction_time = self.attack.construction_time;
+
+        config.defence_price = self.defense.price;
+        config.defence_construction_time = self.defense.construction_time;
+
+        config.energy_price = self.energy.price;
+        config.energy_construction_time = self.energy.construction_time;
+        config.energy_generated_tower = self.energy.energy_generated_per_turn;
+
+        config.tesla_damage = self.tesla.weapon_damage;
+        config.tesla_cooldown = self.tesla.weapon_cooldown_period;
+        config.tesla_price = self.tesla.price;
+        config.tesla_construction_time = self.tesla.construction_time;
+
+        // A new building can be started every round, on top of the
+        // teslas, so this bounds how many can be under construction.
+        let longest_construction = [config.missile_construction_time, config.defence_construction_time, config.energy_construction_time].iter().cloned().max().unwrap_or(0);
+        if usize::from(longest_construction) + 1 + TESLA_MAX > MAX_CONCURRENT_CONSTRUCTION {
+            return Err(format!("Unsupported construction time {}", longest_construction).into());
+        }
+
+        Ok(())
+    }
+}
+
 impl BuildingState {
-    fn to_bitwise_engine_unconstructed(&self) -> bitwise_engine::UnconstructedBuilding {
+    fn to_bitwise_engine_unconstructed<M: MapSize>(&self) -> bitwise_engine::UnconstructedBuilding {
         bitwise_engine::UnconstructedBuilding {
-            pos: engine::geometry::Point::new(self.x, self.y),
+            pos: engine::geometry::Point::from_map_coordinates::<M>(self.x, self.y),
             construction_time_left: self.construction_time_left as u8, // > 0 check already happened
             building_type: self.convert_building_type()
         }
@@ -178,7 +651,7 @@ impl BuildingState {
 
 
 impl Player {
-    fn map_onto_engine(&self, engine_player: &mut bitwise_engine::Player) {
+    fn map_onto_engine<M: MapSize>(&self, engine_player: &mut bitwise_engine::Player<M>) {
         engine_player.health = self.health;
         engine_player.energy = self.energy;
         engine_player.iron_curtain_available = self.iron_curtain_available;
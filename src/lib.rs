@@ -11,10 +11,8 @@ extern crate rayon;
 
 extern crate arrayvec;
 
-#[macro_use]
-#[cfg(feature = "heuristic-random")]
-extern crate lazy_static;
-
 pub mod input;
 pub mod engine;
 pub mod strategy;
+pub mod arena;
+pub mod replay;
@@ -4,42 +4,129 @@ use time::{PreciseTime, Duration};
 
 use zombot::*;
 use zombot::engine::constants::*;
+use zombot::strategy;
 use zombot::engine::command::Command;
+use zombot::engine::bitboard::{MapSize, Map16x8, Map20x10};
+use zombot::strategy::config::BotConfig;
+use zombot::strategy::budget::SearchBudget;
 
+use std::env;
 use std::error::Error;
+use std::path::Path;
 
 const STATE_PATH: &str = "state.json";
 
 const COMMAND_PATH: &str = "command.txt";
 
+const BOT_CONFIG_PATH: &str = "bot-config.json";
+
+const DECISION_LOG_PATH: &str = "decisions";
+
 use std::fs::File;
 use std::io::prelude::*;
 use std::process;
 
-fn write_command(filename: &str, command: Command) -> Result<(), Box<Error> > {
+fn write_command(filename: &str, command: Command) -> Result<(), Box<dyn Error> > {
     let mut file = File::create(filename)?;
     write!(file, "{}", command)?;
     Ok(())
 }
 
+struct Options {
+    config_path: String,
+    seed: u64,
+    budget: Option<SearchBudget>
+}
+
+/**
+ * The config file can be given with `--config <file>`, otherwise
+ * `bot-config.json` is used. `--budget <unit>:<amount>` overrides the
+ * search budget in the config, and with `--seed <n>` makes the search
+ * reproducible.
+ */
+fn parse_args() -> Result<Options, Box<dyn Error>> {
+    let mut options = Options {
+        config_path: BOT_CONFIG_PATH.to_string(),
+        seed: 0,
+        budget: None
+    };
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--config" => options.config_path = args.next().ok_or("--config needs a file")?,
+            "--seed" => options.seed = args.next().ok_or("--seed needs a number")?.parse()?,
+            "--budget" => options.budget = Some(args.next().ok_or("--budget needs a unit and amount")?.parse()?),
+            other => return Err(format!("Unknown argument: {}", other).into())
+        }
+    }
+    Ok(options)
+}
+
+/**
+ * A broken config shouldn't stop the bot from playing, so it falls
+ * back to the defaults.
+ */
+fn read_bot_config(options: &Options) -> BotConfig {
+    let mut config = if !Path::new(&options.config_path).exists() {
+        BotConfig::default()
+    } else {
+        match input::json::read_bot_config_from_file(&options.config_path) {
+            Ok(config) => config,
+            Err(error) => {
+                println!("Error while parsing bot config, using the defaults: {}", error);
+                BotConfig::default()
+            }
+        }
+    };
+
+    if options.budget.is_some() {
+        config.budget = options.budget;
+    }
+    config
+}
+
+fn choose_move<M: MapSize>(bot_config: &BotConfig, seed: [u8; 16], start_time: PreciseTime, max_time: Duration) -> Command {
+    let state = match input::json::read_state_from_file::<M>(STATE_PATH) {
+        Ok(ok) => ok,
+        Err(error) => {
+            println!("Error while parsing JSON file: {}", error);
+            process::exit(1);
+        }
+    };
+
+    let mut strategy = bot_config.build_strategy::<M>(seed, Path::new(DECISION_LOG_PATH));
+    strategy.choose_move(&state, start_time, max_time)
+}
+
 fn main() {
     let start_time = PreciseTime::now();
     let max_time = Duration::milliseconds(MAX_TIME_MILLIS);
-    
-    let state = match input::json::read_bitwise_state_from_file(STATE_PATH) {
+    let options = match parse_args() {
         Ok(ok) => ok,
         Err(error) => {
-            println!("Error while parsing JSON file: {}", error);
+            println!("{}", error);
             process::exit(1);
         }
     };
-
-    let command = if cfg!(feature = "static-opening") && state.round < strategy::static_opening::STATIC_OPENING_LENGTH {
-        strategy::static_opening::choose_move(&state)
-    } else if cfg!(feature = "full-monte-carlo-tree") {
-        strategy::monte_carlo_tree::choose_move(&state, start_time, max_time)
-    } else {
-        strategy::monte_carlo::choose_move(&state, start_time, max_time)
+    let bot_config = read_bot_config(&options);
+    let seed = strategy::seed_from_u64(options.seed);
+    
+    let command = match input::json::read_map_size_from_file(STATE_PATH) {
+        Ok((width, height)) if width == Map16x8::FULL_MAP_WIDTH && height == Map16x8::MAP_HEIGHT => {
+            choose_move::<Map16x8>(&bot_config, seed, start_time, max_time)
+        },
+        Ok((width, height)) if width == Map20x10::FULL_MAP_WIDTH && height == Map20x10::MAP_HEIGHT => {
+            choose_move::<Map20x10>(&bot_config, seed, start_time, max_time)
+        },
+        Ok((width, height)) => {
+            println!("Unsupported map size: {}x{}", width, height);
+            process::exit(1);
+        },
+        Err(error) => {
+            println!("Error while parsing JSON file: {}", error);
+            process::exit(1);
+        }
     };
 
     match write_command(COMMAND_PATH, command) {
@@ -52,4 +139,3 @@ fn main() {
 
     println!("Elapsed time: {}", start_time.to(PreciseTime::now()));
 }
-
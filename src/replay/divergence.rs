@@ -0,0 +1,205 @@
+use engine::command::{Command, BuildingType};
+use engine::geometry::Point;
+use engine::bitwise_engine::{BitwiseGameState, Player};
+use engine::bitboard::MapSize;
+use engine::cell::Cell;
+use engine::display;
+use replay::import;
+use replay::recorder::{self, PLAYER_COMMAND_FILE, OPPONENT_COMMAND_FILE};
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+
+/**
+ * One way in which the engine's prediction of a round differs from
+ * what actually happened.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Difference {
+    Field {
+        name: String,
+        predicted: String,
+        actual: String
+    },
+    /// `x` and `y` are coordinates on the full map.
+    Cell {
+        x: u8,
+        y: u8,
+        predicted: Cell,
+        actual: Cell
+    }
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Difference::Field { name, predicted, actual } => write!(f, "{}: predicted {}, actual {}", name, predicted, actual),
+            Difference::Cell { x, y, predicted, actual } => {
+                write!(f, "cell {},{}:", x, y)?;
+                if predicted.building != actual.building {
+                    write!(f, " building predicted {}, actual {};", describe_building(predicted), describe_building(actual))?;
+                }
+                if predicted.outgoing_missiles != actual.outgoing_missiles || predicted.incoming_missiles != actual.incoming_missiles {
+                    write!(f, " missiles (outgoing/incoming) predicted {}/{}, actual {}/{};",
+                           predicted.outgoing_missiles, predicted.incoming_missiles,
+                           actual.outgoing_missiles, actual.incoming_missiles)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn describe_building(cell: &Cell) -> String {
+    match cell.building {
+        None => "nothing".to_string(),
+        Some(b) if b.is_under_construction() =>
+            format!("{:?} ({} rounds of construction left)", b.building_type, b.construction_time_left),
+        Some(b) => match b.building_type {
+            BuildingType::Attack | BuildingType::Tesla =>
+                format!("{:?} (health {}, cooldown {})", b.building_type, b.health_tiers, b.weapon_cooldown),
+            _ => format!("{:?} (health {})", b.building_type, b.health_tiers)
+        }
+    }
+}
+
+/**
+ * Lists everything that differs between two states. Missiles, teslas
+ * and missile towers are compared cell by cell, so the order they're
+ * stored in doesn't matter.
+ */
+pub fn diff_states<M: MapSize>(predicted: &BitwiseGameState<M>, actual: &BitwiseGameState<M>) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    let mut field = |name: &str, predicted: String, actual: String| {
+        if predicted != actual {
+            differences.push(Difference::Field { name: name.to_string(), predicted, actual });
+        }
+    };
+
+    field("status", format!("{:?}", predicted.status), format!("{:?}", actual.status));
+    field("round", predicted.round.to_string(), actual.round.to_string());
+    field("config", format!("{:?}", predicted.config), format!("{:?}", actual.config));
+    for &(player_type, is_player) in &[('A', true), ('B', false)] {
+        let (p, a) = if is_player {
+            (&predicted.player, &actual.player)
+        } else {
+            (&predicted.opponent, &actual.opponent)
+        };
+        field(&format!("{} energy", player_type), p.energy.to_string(), a.energy.to_string());
+        field(&format!("{} health", player_type), p.health.to_string(), a.health.to_string());
+        field(&format!("{} iron curtain available", player_type), p.iron_curtain_available.to_string(), a.iron_curtain_available.to_string());
+        field(&format!("{} iron curtain remaining", player_type), p.iron_curtain_remaining.to_string(), a.iron_curtain_remaining.to_string());
+        field(&format!("{} tesla ages", player_type), tesla_ages(p), tesla_ages(a));
+    }
+
+    for y in 0..M::MAP_HEIGHT {
+        for x in 0..M::FULL_MAP_WIDTH {
+            let is_player_half = x < M::SINGLE_MAP_WIDTH;
+            let point = Point::from_map_coordinates::<M>(x, y);
+            let predicted_cell = predicted.cell(is_player_half, point);
+            let actual_cell = actual.cell(is_player_half, point);
+            if predicted_cell != actual_cell {
+                differences.push(Difference::Cell { x, y, predicted: predicted_cell, actual: actual_cell });
+            }
+        }
+    }
+
+    differences
+}
+
+fn tesla_ages<M: MapSize>(player: &Player<M>) -> String {
+    let mut teslas: Vec<_> = player.tesla_cooldowns.iter().map(|t| (t.pos, t.age)).collect();
+    teslas.sort();
+    teslas.iter()
+        .map(|(pos, age)| format!("{},{}:{}", pos.x(), pos.y(), age))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/**
+ * Draws the predicted and actual boards next to each other, with a
+ * third board marking the cells that differ.
+ */
+pub fn side_by_side_boards<M: MapSize>(predicted: &BitwiseGameState<M>, actual: &BitwiseGameState<M>) -> String {
+    let width = M::FULL_MAP_WIDTH as usize * 3;
+    let predicted_lines = display::board_lines(predicted, false);
+    let actual_lines = display::board_lines(actual, false);
+
+    let mut result = format!("{:w$} | {:w$} | Differences\n", "Predicted", "Actual", w = width);
+    for (y, (p, a)) in predicted_lines.iter().zip(actual_lines.iter()).enumerate() {
+        let marks: String = (0..M::FULL_MAP_WIDTH).map(|x| {
+            let is_player_half = x < M::SINGLE_MAP_WIDTH;
+            let point = Point::from_map_coordinates::<M>(x, y as u8);
+            if predicted.cell(is_player_half, point) == actual.cell(is_player_half, point) { " . " } else { " X " }
+        }).collect();
+        result.push_str(&format!("{} | {} | {}\n", p, a, marks));
+    }
+    result
+}
+
+/**
+ * A round where simulating the recorded commands didn't give the
+ * recorded next state.
+ */
+#[derive(Debug, Clone)]
+pub struct Divergence<M: MapSize> {
+    pub round: u16,
+    pub player_command: Command,
+    pub opponent_command: Command,
+    pub predicted: BitwiseGameState<M>,
+    pub actual: BitwiseGameState<M>,
+    pub differences: Vec<Difference>
+}
+
+impl<M: MapSize> Divergence<M> {
+    pub fn new(round: u16, player_command: Command, opponent_command: Command, predicted: BitwiseGameState<M>, actual: BitwiseGameState<M>) -> Divergence<M> {
+        let differences = diff_states(&predicted, &actual);
+        Divergence { round, player_command, opponent_command, predicted, actual, differences }
+    }
+}
+
+impl<M: MapSize> fmt::Display for Divergence<M> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Round {} -> {}, A played {:?}, B played {:?}", self.round, self.round + 1, self.player_command, self.opponent_command)?;
+        for difference in &self.differences {
+            writeln!(f, "  {}", difference)?;
+        }
+        writeln!(f)?;
+        write!(f, "{}", side_by_side_boards(&self.predicted, &self.actual))
+    }
+}
+
+pub struct DivergenceReport<M: MapSize> {
+    /// The number of rounds with commands, not counting the final state.
+    pub rounds: u16,
+    pub divergences: Vec<Divergence<M>>
+}
+
+/**
+ * Simulates each round of a replay in the `Round NNN/state.json`
+ * layout, and returns every round where the engine disagrees with the
+ * recorded next state. Each round starts from the recorded state, so a
+ * single mistake is only reported once.
+ */
+pub fn find_divergences<M: MapSize>(replay_folder: &Path) -> Result<DivergenceReport<M>, Box<dyn Error>> {
+    let rounds = import::count_rounds(replay_folder)? - 1;
+    let mut divergences = Vec::new();
+
+    let mut state = import::read_round_state::<M>(replay_folder, 0)?;
+    for round in 0..rounds {
+        let round_folder = recorder::round_folder(replay_folder, round);
+        let player_command = import::read_round_command::<M>(&round_folder.join(PLAYER_COMMAND_FILE))?;
+        let opponent_command = import::read_round_command::<M>(&round_folder.join(OPPONENT_COMMAND_FILE))?;
+        let next_state = import::read_round_state::<M>(replay_folder, round + 1)?;
+
+        // Invalid commands do nothing, the same as in the game engine
+        let _ = state.try_simulate(player_command, opponent_command);
+        if state.sorted() != next_state.sorted() {
+            divergences.push(Divergence::new(round, player_command, opponent_command, state, next_state.clone()));
+        }
+        state = next_state;
+    }
+
+    Ok(DivergenceReport { rounds, divergences })
+}
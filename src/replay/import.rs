@@ -0,0 +1,153 @@
+use engine::command::Command;
+use engine::bitwise_engine::BitwiseGameState;
+use engine::bitboard::{MapSize, Map16x8, Map20x10};
+use input;
+use replay::divergence;
+use replay::recorder::{self, STATE_FILE, PLAYER_COMMAND_FILE, OPPONENT_COMMAND_FILE};
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of the state file in each player's folder of a server replay.
+pub const REPLAY_STATE_FILE: &str = "JsonMap.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportReport {
+    /// The number of rounds with commands, not counting the final state.
+    pub rounds: u16,
+    /// Rounds where simulating the recorded commands didn't give the
+    /// state recorded for the next round.
+    pub mismatched_rounds: Vec<u16>
+}
+
+/**
+ * Converts a server replay, with a folder per player in each round,
+ * into the `Round NNN/state.json` layout used by the tests and the
+ * replay recorder. The state is taken from player A's point of view.
+ *
+ * Afterwards every round is read back and simulated, and any rounds
+ * where the engine disagrees with the replay are reported.
+ */
+pub fn import_replay(replay_folder: &Path, output_folder: &Path) -> Result<ImportReport, Box<dyn Error>> {
+    let round_folders = sorted_subfolders(replay_folder)?;
+    if round_folders.is_empty() {
+        return Err(format!("No rounds found in {}", replay_folder.display()).into());
+    }
+
+    fs::create_dir_all(output_folder)?;
+    for (round, round_folder) in round_folders.iter().enumerate() {
+        let is_final_round = round == round_folders.len() - 1;
+        let (player_folder, opponent_folder) = identify_player_folders(round_folder)?;
+        let output_round_folder = recorder::round_folder(output_folder, round as u16);
+        fs::create_dir_all(&output_round_folder)?;
+
+        copy_file(&player_folder.join(REPLAY_STATE_FILE), &output_round_folder.join(STATE_FILE), true)?;
+        copy_file(&player_folder.join(PLAYER_COMMAND_FILE), &output_round_folder.join(PLAYER_COMMAND_FILE), !is_final_round)?;
+        copy_file(&opponent_folder.join(PLAYER_COMMAND_FILE), &output_round_folder.join(OPPONENT_COMMAND_FILE), !is_final_round)?;
+    }
+
+    validate_replay(output_folder)
+}
+
+/**
+ * Reads every round of a replay in the `Round NNN/state.json` layout,
+ * and checks that simulating each round's commands gives the next
+ * round's state.
+ */
+pub fn validate_replay(replay_folder: &Path) -> Result<ImportReport, Box<dyn Error>> {
+    let first_state = recorder::round_folder(replay_folder, 0).join(STATE_FILE);
+    match input::json::read_map_size_from_file(&first_state.to_string_lossy())? {
+        (width, height) if width == Map16x8::FULL_MAP_WIDTH && height == Map16x8::MAP_HEIGHT => validate_rounds::<Map16x8>(replay_folder),
+        (width, height) if width == Map20x10::FULL_MAP_WIDTH && height == Map20x10::MAP_HEIGHT => validate_rounds::<Map20x10>(replay_folder),
+        (width, height) => Err(format!("Unsupported map size: {}x{}", width, height).into())
+    }
+}
+
+fn validate_rounds<M: MapSize>(replay_folder: &Path) -> Result<ImportReport, Box<dyn Error>> {
+    let report = divergence::find_divergences::<M>(replay_folder)?;
+    Ok(ImportReport {
+        rounds: report.rounds,
+        mismatched_rounds: report.divergences.iter().map(|d| d.round).collect()
+    })
+}
+
+/**
+ * The number of round folders in a replay, including the last one,
+ * which only has the final state.
+ */
+pub fn count_rounds(replay_folder: &Path) -> Result<u16, Box<dyn Error>> {
+    let rounds = sorted_subfolders(replay_folder)?.len() as u16;
+    if rounds == 0 {
+        return Err(format!("No rounds found in {}", replay_folder.display()).into());
+    }
+    Ok(rounds)
+}
+
+pub fn read_round_state<M: MapSize>(replay_folder: &Path, round: u16) -> Result<BitwiseGameState<M>, Box<dyn Error>> {
+    let path = recorder::round_folder(replay_folder, round).join(STATE_FILE);
+    input::json::read_state_from_file::<M>(&path.to_string_lossy())
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e).into())
+}
+
+pub fn read_round_command<M: MapSize>(path: &Path) -> Result<Command, Box<dyn Error>> {
+    input::command::read_command_from_file::<M>(&path.to_string_lossy())
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e).into())
+}
+
+/**
+ * Finds which of the two player folders in a round belongs to player A.
+ * Each bot is given the map with its own half on the left, so this
+ * uses the `playerType` that owns the left of each folder's map. If
+ * both folders were given the same map, it falls back to the server's
+ * naming of the folders, which start with the player's letter.
+ */
+fn identify_player_folders(round_folder: &Path) -> Result<(PathBuf, PathBuf), Box<dyn Error>> {
+    let player_folders = sorted_subfolders(round_folder)?;
+    if player_folders.len() != 2 {
+        return Err(format!("Expected 2 player folders in {} but found {}", round_folder.display(), player_folders.len()).into());
+    }
+
+    let mut player_types = Vec::with_capacity(2);
+    for folder in &player_folders {
+        let state_path = folder.join(REPLAY_STATE_FILE);
+        let player_type = input::json::read_viewing_player_type_from_file(&state_path.to_string_lossy())
+            .map_err(|e| format!("Could not read {}: {}", state_path.display(), e))?;
+        player_types.push(player_type);
+    }
+    if player_types[0] == player_types[1] {
+        player_types = player_folders.iter()
+            .map(|folder| folder.file_name().and_then(|name| name.to_string_lossy().chars().next()).unwrap_or(' '))
+            .collect();
+    }
+
+    match (player_types[0], player_types[1]) {
+        ('A', 'B') => Ok((player_folders[0].clone(), player_folders[1].clone())),
+        ('B', 'A') => Ok((player_folders[1].clone(), player_folders[0].clone())),
+        _ => Err(format!("Could not tell which player folder in {} is player A", round_folder.display()).into())
+    }
+}
+
+fn copy_file(from: &Path, to: &Path, required: bool) -> Result<(), Box<dyn Error>> {
+    if !from.exists() {
+        return if required {
+            Err(format!("Missing file {}", from.display()).into())
+        } else {
+            Ok(())
+        };
+    }
+    fs::copy(from, to)?;
+    Ok(())
+}
+
+fn sorted_subfolders(folder: &Path) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    let mut subfolders = Vec::new();
+    for entry in fs::read_dir(folder).map_err(|e| format!("Could not read {}: {}", folder.display(), e))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            subfolders.push(path);
+        }
+    }
+    subfolders.sort();
+    Ok(subfolders)
+}
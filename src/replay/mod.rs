@@ -0,0 +1,4 @@
+pub mod recorder;
+pub mod import;
+pub mod divergence;
+pub mod viewer;
@@ -0,0 +1,80 @@
+use engine::command::Command;
+use engine::status::GameStatus;
+use engine::bitwise_engine::BitwiseGameState;
+use engine::bitboard::MapSize;
+use input;
+use strategy::monte_carlo::{self, RandomMoveOptions};
+
+use rand::Rng;
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const STATE_FILE: &str = "state.json";
+pub const PLAYER_COMMAND_FILE: &str = "PlayerCommand.txt";
+pub const OPPONENT_COMMAND_FILE: &str = "OpponentCommand.txt";
+/// The bot's decision log for the round, if it wrote one.
+pub const DECISION_FILE: &str = "decision.json";
+
+pub fn round_folder(replay_folder: &Path, round: u16) -> PathBuf {
+    replay_folder.join(format!("Round {:03}", round))
+}
+
+/**
+ * Writes a game in the same layout that `import-replay` makes from the
+ * server replays: a `Round NNN` folder for each round, with the
+ * state at the start of the round and the commands both players made
+ * in it. The last round only has the final state.
+ */
+pub struct ReplayRecorder {
+    folder: PathBuf
+}
+
+impl ReplayRecorder {
+    pub fn create(folder: &Path) -> Result<ReplayRecorder, Box<dyn Error>> {
+        fs::create_dir_all(folder)?;
+        Ok(ReplayRecorder {
+            folder: folder.to_path_buf()
+        })
+    }
+
+    pub fn folder(&self) -> &Path {
+        &self.folder
+    }
+
+    pub fn record_round<M: MapSize>(&mut self, state: &BitwiseGameState<M>, player_command: Command, opponent_command: Command) -> Result<(), Box<dyn Error>> {
+        let folder = self.record_state(state)?;
+        input::command::write_replay_command_to_file(player_command, &folder.join(PLAYER_COMMAND_FILE).to_string_lossy())?;
+        input::command::write_replay_command_to_file(opponent_command, &folder.join(OPPONENT_COMMAND_FILE).to_string_lossy())?;
+        Ok(())
+    }
+
+    pub fn record_final_state<M: MapSize>(&mut self, state: &BitwiseGameState<M>) -> Result<(), Box<dyn Error>> {
+        self.record_state(state)?;
+        Ok(())
+    }
+
+    fn record_state<M: MapSize>(&mut self, state: &BitwiseGameState<M>) -> Result<PathBuf, Box<dyn Error>> {
+        let folder = round_folder(&self.folder, state.round);
+        fs::create_dir_all(&folder)?;
+        input::json::write_state_to_file(state, &folder.join(STATE_FILE).to_string_lossy())?;
+        Ok(folder)
+    }
+}
+
+/**
+ * Plays out the same kind of random game as the Monte Carlo search
+ * uses to score a move, and records it.
+ */
+pub fn record_rollout<M: MapSize, R: Rng>(state: &BitwiseGameState<M>, random_moves: &RandomMoveOptions, rng: &mut R, recorder: &mut ReplayRecorder) -> Result<GameStatus, Box<dyn Error>> {
+    let mut state = state.clone();
+    while state.status == GameStatus::Continue && state.round < state.config.max_rounds {
+        let player_command = monte_carlo::random_move(&state.player, &state.opponent, &state.config, random_moves, rng);
+        let opponent_command = monte_carlo::random_move(&state.opponent, &state.player, &state.config, random_moves, rng);
+        recorder.record_round(&state, player_command, opponent_command)?;
+        state.simulate(player_command, opponent_command);
+    }
+    recorder.record_final_state(&state)?;
+    Ok(state.status)
+}
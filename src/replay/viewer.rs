@@ -0,0 +1,118 @@
+use engine::command::Command;
+use engine::bitwise_engine::BitwiseGameState;
+use engine::bitboard::MapSize;
+use input;
+use replay::import;
+use replay::divergence;
+use replay::recorder::{self, PLAYER_COMMAND_FILE, OPPONENT_COMMAND_FILE, DECISION_FILE};
+use strategy::decision_log::DecisionRecord;
+
+use std::error::Error;
+use std::path::Path;
+
+/// How many of the bot's candidate moves to show for each round.
+const SHOWN_CANDIDATES: usize = 10;
+
+/**
+ * A whole replay in memory, for stepping through it a round at a time.
+ */
+pub struct Replay<M: MapSize> {
+    /// The state at the start of each round, and the final state.
+    pub states: Vec<BitwiseGameState<M>>,
+    /// The commands made in each round, for all but the final state.
+    pub commands: Vec<(Command, Command)>,
+    /// Player A's decision log for each round, where there is one.
+    pub decisions: Vec<Option<DecisionRecord>>
+}
+
+impl<M: MapSize> Replay<M> {
+    /**
+     * Reads a replay in the `Round NNN/state.json` layout. Decision
+     * logs are read from `decision_folder`, which has the same layout
+     * and is usually the replay folder itself.
+     */
+    pub fn load(replay_folder: &Path, decision_folder: &Path) -> Result<Replay<M>, Box<dyn Error>> {
+        let round_count = import::count_rounds(replay_folder)?;
+        let mut states = Vec::with_capacity(round_count as usize);
+        let mut commands = Vec::with_capacity(round_count as usize);
+        let mut decisions = Vec::with_capacity(round_count as usize);
+
+        for round in 0..round_count {
+            states.push(import::read_round_state::<M>(replay_folder, round)?);
+
+            let decision_path = recorder::round_folder(decision_folder, round).join(DECISION_FILE);
+            decisions.push(if decision_path.exists() {
+                Some(input::json::read_decision_record_from_file(&decision_path.to_string_lossy())
+                     .map_err(|e| format!("Could not read {}: {}", decision_path.display(), e))?)
+            } else {
+                None
+            });
+
+            if round < round_count - 1 {
+                let round_folder = recorder::round_folder(replay_folder, round);
+                commands.push((
+                    import::read_round_command::<M>(&round_folder.join(PLAYER_COMMAND_FILE))?,
+                    import::read_round_command::<M>(&round_folder.join(OPPONENT_COMMAND_FILE))?
+                ));
+            }
+        }
+
+        Ok(Replay { states, commands, decisions })
+    }
+
+    pub fn last_round(&self) -> u16 {
+        self.states.len() as u16 - 1
+    }
+
+    /**
+     * Draws one round: the state at the start of it, both commands,
+     * the engine's prediction of the next state next to the recorded
+     * one, and the bot's best scoring moves if they were logged.
+     */
+    pub fn render_round(&self, round: u16, colour: bool) -> String {
+        let round = round.min(self.last_round());
+        let state = &self.states[round as usize];
+        let mut result = if colour { format!("{:#}", state) } else { format!("{}", state) };
+        result.push('\n');
+
+        match self.commands.get(round as usize) {
+            Some(&(player_command, opponent_command)) => {
+                result.push_str(&format!("A played {:?}, B played {:?}\n\n", player_command, opponent_command));
+                let mut predicted = state.clone();
+                let (_, player_result, opponent_result) = predicted.try_simulate(player_command, opponent_command);
+                for &(player, command_result) in &[('A', player_result), ('B', opponent_result)] {
+                    if let Err(error) = command_result {
+                        result.push_str(&format!("{}'s command was ignored, because {}\n\n", player, error));
+                    }
+                }
+                let actual = &self.states[round as usize + 1];
+                result.push_str(&divergence::side_by_side_boards(&predicted, actual));
+                let differences = divergence::diff_states(&predicted, actual);
+                if differences.is_empty() {
+                    result.push_str("The engine predicts the next round exactly\n");
+                } else {
+                    for difference in &differences {
+                        result.push_str(&format!("  {}\n", difference));
+                    }
+                }
+            },
+            None => result.push_str("Final state\n")
+        }
+
+        if let Some(ref decision) = self.decisions[round as usize] {
+            result.push_str(&format!("\nThe bot chose '{}'. Best scoring moves:\n", decision.choice));
+            let mut candidates: Vec<_> = decision.candidates.iter().collect();
+            candidates.sort_by_key(|c| -c.win_ratio);
+            for candidate in candidates.iter().take(SHOWN_CANDIDATES) {
+                let score = if decision.weighted_win_ratio {
+                    format!("{:>8}", candidate.win_ratio)
+                } else {
+                    format!("{:>7.2}%", f64::from(candidate.win_ratio) / 100.0)
+                };
+                result.push_str(&format!("  {:>10} {} from {} games\n", candidate.command, score, candidate.attempts));
+            }
+        }
+
+        result
+    }
+}
@@ -0,0 +1,124 @@
+use time::{Duration, PreciseTime};
+
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/**
+ * How much searching a strategy does before it makes its choice.
+ *
+ * Only the time budget depends on how fast the machine is. The others
+ * count the work done, so with a fixed seed the same state always gets
+ * the same move, however many threads the search has.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SearchBudget {
+    /// Milliseconds from the start of the turn.
+    TimeMillis(i64),
+    /// Simulated games, counted over all of the candidate moves.
+    Rollouts(u32),
+    /// Simulated games starting with each candidate move. The
+    /// candidates that `monte_carlo` discards early get fewer.
+    RolloutsPerCandidate(u32),
+    /// Nodes added to the search tree, where a search that can't add
+    /// one counts as one anyway. `monte_carlo` doesn't keep a tree, so
+    /// it counts each simulated game as a node.
+    Nodes(u32)
+}
+
+/**
+ * The work a search has done so far, in each of the units that a
+ * `SearchBudget` can be given in.
+ */
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchProgress {
+    pub rollouts: u32,
+    /// The fewest games started with any candidate that's still being
+    /// searched.
+    pub rollouts_per_candidate: u32,
+    pub nodes: u32
+}
+
+impl SearchBudget {
+    /**
+     * The budget from the bot config if there is one, otherwise the
+     * time limit for the turn.
+     */
+    pub fn or_time(budget: Option<SearchBudget>, max_time: Duration) -> SearchBudget {
+        budget.unwrap_or_else(|| SearchBudget::TimeMillis(max_time.num_milliseconds()))
+    }
+
+    pub fn spent(&self, start_time: PreciseTime, progress: &SearchProgress) -> bool {
+        match *self {
+            SearchBudget::TimeMillis(millis) => start_time.to(PreciseTime::now()) > Duration::milliseconds(millis),
+            SearchBudget::Rollouts(rollouts) => progress.rollouts >= rollouts,
+            SearchBudget::RolloutsPerCandidate(rollouts) => progress.rollouts_per_candidate >= rollouts,
+            SearchBudget::Nodes(nodes) => progress.nodes >= nodes
+        }
+    }
+
+    /// Whether the budget depends on how fast the machine is.
+    pub fn is_time(&self) -> bool {
+        matches!(*self, SearchBudget::TimeMillis(_))
+    }
+
+    /**
+     * One of `parts` searches run side by side, which together spend
+     * this budget. They all have the whole time, and split any work
+     * between them.
+     */
+    pub fn share(&self, parts: u32) -> SearchBudget {
+        let parts = parts.max(1);
+        let split = |amount: u32| amount.div_ceil(parts);
+        match *self {
+            SearchBudget::TimeMillis(millis) => SearchBudget::TimeMillis(millis),
+            SearchBudget::Rollouts(rollouts) => SearchBudget::Rollouts(split(rollouts)),
+            SearchBudget::RolloutsPerCandidate(rollouts) => SearchBudget::RolloutsPerCandidate(split(rollouts)),
+            SearchBudget::Nodes(nodes) => SearchBudget::Nodes(split(nodes))
+        }
+    }
+
+    /// The point `numerator / denominator` of the way through the budget.
+    pub fn fraction(&self, numerator: u32, denominator: u32) -> SearchBudget {
+        let scale = |amount: u32| (u64::from(amount) * u64::from(numerator) / u64::from(denominator)) as u32;
+        match *self {
+            SearchBudget::TimeMillis(millis) => SearchBudget::TimeMillis(millis * i64::from(numerator) / i64::from(denominator)),
+            SearchBudget::Rollouts(rollouts) => SearchBudget::Rollouts(scale(rollouts)),
+            SearchBudget::RolloutsPerCandidate(rollouts) => SearchBudget::RolloutsPerCandidate(scale(rollouts)),
+            SearchBudget::Nodes(nodes) => SearchBudget::Nodes(scale(nodes))
+        }
+    }
+}
+
+impl FromStr for SearchBudget {
+    type Err = Box<dyn Error>;
+
+    /**
+     * Budgets are given on the command line as `<unit>:<amount>`, for
+     * example `rollouts:20000`.
+     */
+    fn from_str(s: &str) -> Result<SearchBudget, Box<dyn Error>> {
+        let mut parts = s.splitn(2, ':');
+        let unit = parts.next().unwrap_or("");
+        let amount = parts.next().ok_or_else(|| format!("Expected '<unit>:<amount>' but found '{}'", s))?;
+        match unit {
+            "time" => Ok(SearchBudget::TimeMillis(amount.parse()?)),
+            "rollouts" => Ok(SearchBudget::Rollouts(amount.parse()?)),
+            "rollouts-per-candidate" => Ok(SearchBudget::RolloutsPerCandidate(amount.parse()?)),
+            "nodes" => Ok(SearchBudget::Nodes(amount.parse()?)),
+            _ => Err(format!("Unknown search budget unit '{}'", unit).into())
+        }
+    }
+}
+
+impl fmt::Display for SearchBudget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SearchBudget::TimeMillis(millis) => write!(f, "time:{}", millis),
+            SearchBudget::Rollouts(rollouts) => write!(f, "rollouts:{}", rollouts),
+            SearchBudget::RolloutsPerCandidate(rollouts) => write!(f, "rollouts-per-candidate:{}", rollouts),
+            SearchBudget::Nodes(nodes) => write!(f, "nodes:{}", nodes)
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use engine::bitboard::MapSize;
+use strategy::Strategy;
+use strategy::budget::SearchBudget;
+use strategy::monte_carlo::{MonteCarlo, MonteCarloOptions, RandomMoveOptions};
+use strategy::monte_carlo_tree::{MonteCarloTree, TREE_FILE};
+use strategy::static_opening::StaticOpening;
+use strategy::random::Random;
+
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StrategyType {
+    MonteCarlo,
+    MonteCarloTree,
+    Random
+}
+
+/**
+ * Which strategy the bot plays, and the options that tune it. This is
+ * read from `bot-config.json`, and any fields left out keep their
+ * default values.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct BotConfig {
+    pub strategy: StrategyType,
+    pub static_opening: bool,
+    pub energy_cutoff: bool,
+    pub discard_poor_performers: bool,
+    pub heuristic_random: bool,
+    pub weighted_win_ratio: bool,
+    /// Write a decision record for every round. Only the `monte_carlo`
+    /// strategy writes them.
+    pub decision_log: bool,
+    /// How much to search each turn, instead of until the time limit.
+    /// Any budget other than time gives the same move for the same
+    /// seed on every run.
+    pub budget: Option<SearchBudget>,
+    /// The number of trees that `monte_carlo_tree` searches in
+    /// parallel. By default there's one for each thread when searching
+    /// for a time, and a fixed number for any other budget.
+    pub trees: Option<usize>,
+    /// The most nodes that the `monte_carlo_tree` trees keep in memory
+    /// between them. The least visited parts are pruned to stay under it.
+    pub max_nodes: Option<usize>,
+    /// Save the `monte_carlo_tree` search tree at the end of each turn,
+    /// and carry on from it in the next turn.
+    pub reuse_tree: bool
+}
+
+impl Default for BotConfig {
+    fn default() -> BotConfig {
+        BotConfig {
+            strategy: StrategyType::MonteCarlo,
+            static_opening: true,
+            energy_cutoff: true,
+            discard_poor_performers: true,
+            heuristic_random: false,
+            weighted_win_ratio: true,
+            decision_log: false,
+            budget: None,
+            trees: None,
+            max_nodes: None,
+            reuse_tree: false
+        }
+    }
+}
+
+impl BotConfig {
+    pub fn random_move_options(&self) -> RandomMoveOptions {
+        RandomMoveOptions {
+            heuristic: self.heuristic_random,
+            energy_cutoff: self.energy_cutoff
+        }
+    }
+
+    pub fn monte_carlo_options(&self) -> MonteCarloOptions {
+        MonteCarloOptions {
+            discard_poor_performers: self.discard_poor_performers,
+            weighted_win_ratio: self.weighted_win_ratio,
+            random_moves: self.random_move_options()
+        }
+    }
+
+    /**
+     * The seed starts the random moves of every strategy, including
+     * the ones in their simulated games. Decision records are written
+     * to `decision_folder` if `decision_log` is set, and the search
+     * tree is saved there between turns if `reuse_tree` is set.
+     */
+    pub fn build_strategy<M: MapSize>(&self, seed: [u8; 16], decision_folder: &Path) -> Box<dyn Strategy<M>> {
+        let decision_log = if self.decision_log { Some(decision_folder.to_path_buf()) } else { None };
+        let strategy: Box<dyn Strategy<M>> = match self.strategy {
+            StrategyType::MonteCarlo => Box::new(MonteCarlo {
+                options: self.monte_carlo_options(),
+                seed,
+                budget: self.budget,
+                decision_log
+            }),
+            StrategyType::MonteCarloTree => Box::new(MonteCarloTree {
+                random_moves: self.random_move_options(),
+                seed,
+                budget: self.budget,
+                trees: self.trees,
+                max_nodes: self.max_nodes,
+                tree_file: if self.reuse_tree { Some(decision_folder.join(TREE_FILE)) } else { None }
+            }),
+            StrategyType::Random => Box::new(Random::new(self.random_move_options(), seed))
+        };
+
+        if self.static_opening {
+            Box::new(StaticOpening { fallback: strategy })
+        } else {
+            strategy
+        }
+    }
+}
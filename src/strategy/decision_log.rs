@@ -0,0 +1,38 @@
+/**
+ * What the bot thought of its options in one round, so that its
+ * choices can be looked at after the game. Commands are written the
+ * same way as in `PlayerCommand.txt`.
+ */
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DecisionRecord {
+    pub round: u16,
+    /// Whether the win ratios are weighted by how many towers were
+    /// left at the end of each game.
+    pub weighted_win_ratio: bool,
+    pub candidates: Vec<CandidateRecord>,
+    /// The candidates that were dropped after each phase of discarding
+    /// poor performers, earliest first.
+    pub pruned: Vec<Vec<String>>,
+    pub choice: String
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct CandidateRecord {
+    pub command: String,
+    /// The command can't be afforded yet, so the bot would do nothing
+    /// this round and build it once it can.
+    pub starts_with_nothing: bool,
+    pub attempts: u32,
+    pub victories: u32,
+    pub defeats: u32,
+    pub draws: u32,
+    /// Games that reached the round limit.
+    pub stalemates: u32,
+    pub victory_score: i32,
+    pub defeat_score: i32,
+    /// `CommandScore::win_ratio`, weighted or not as the bot was
+    /// configured. Unweighted ratios are out of 10000.
+    pub win_ratio: i32
+}
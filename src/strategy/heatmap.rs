@@ -0,0 +1,202 @@
+use engine::command::{Command, BuildingType};
+use engine::geometry::Point;
+use engine::bitboard::MapSize;
+use input;
+use strategy::decision_log::DecisionRecord;
+
+use std::error::Error;
+
+use std::fmt::Write;
+
+/// The order the grids are printed and drawn in.
+pub const HEATMAP_BUILDINGS: [BuildingType; 4] = [BuildingType::Energy, BuildingType::Attack, BuildingType::Defence, BuildingType::Tesla];
+
+const CELL_PIXELS: usize = 24;
+const GAP_PIXELS: usize = 12;
+const LABEL_PIXELS: usize = 20;
+
+/**
+ * The win ratio of building one type of building in each cell of the
+ * player's half of the map, in the player's own coordinates. Cells
+ * that weren't considered have no value.
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heatmap {
+    pub building: BuildingType,
+    pub width: u8,
+    pub height: u8,
+    values: Vec<Option<i32>>
+}
+
+impl Heatmap {
+    pub fn new(building: BuildingType, width: u8, height: u8) -> Heatmap {
+        Heatmap {
+            building, width, height,
+            values: vec!(None; width as usize * height as usize)
+        }
+    }
+
+    pub fn get(&self, point: Point) -> Option<i32> {
+        self.values[self.index(point)]
+    }
+
+    /// Only the first value given for a cell is kept.
+    pub fn set(&mut self, point: Point, value: i32) {
+        let index = self.index(point);
+        if self.values[index].is_none() {
+            self.values[index] = Some(value);
+        }
+    }
+
+    fn index(&self, point: Point) -> usize {
+        point.y() as usize * self.width as usize + point.x() as usize
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self.building {
+            BuildingType::Energy => "ENERGY",
+            BuildingType::Attack => "ATTACK",
+            BuildingType::Defence => "DEFENCE",
+            BuildingType::Tesla => "TESLA"
+        }
+    }
+
+    fn values(&self) -> impl Iterator<Item=i32> + '_ {
+        self.values.iter().filter_map(|&v| v)
+    }
+}
+
+/**
+ * Sorts the scores for build commands into a heatmap for each building
+ * type, in the order of `HEATMAP_BUILDINGS`.
+ */
+pub fn building_heatmaps<M: MapSize, I: IntoIterator<Item=(Command, i32)>>(scores: I) -> Vec<Heatmap> {
+    let mut heatmaps: Vec<Heatmap> = HEATMAP_BUILDINGS.iter()
+        .map(|&b| Heatmap::new(b, M::SINGLE_MAP_WIDTH, M::MAP_HEIGHT))
+        .collect();
+    for (command, score) in scores {
+        if let Command::Build(point, building) = command {
+            if let Some(heatmap) = heatmaps.iter_mut().find(|h| h.building == building) {
+                heatmap.set(point, score);
+            }
+        }
+    }
+    heatmaps
+}
+
+/**
+ * Reads the heatmaps back out of a decision log, where the commands
+ * are written as they would be in `PlayerCommand.txt`.
+ */
+pub fn decision_heatmaps<M: MapSize>(record: &DecisionRecord) -> Result<Vec<Heatmap>, Box<dyn Error>> {
+    let mut scores = Vec::with_capacity(record.candidates.len());
+    for candidate in &record.candidates {
+        scores.push((input::command::parse_command::<M>(&candidate.command)?, candidate.win_ratio));
+    }
+    Ok(building_heatmaps::<M, _>(scores))
+}
+
+pub const CSV_HEADER: &str = "round,building,x,y,win_ratio";
+
+/**
+ * One line for each cell with a value, under `CSV_HEADER`, so that a
+ * whole game can go in one file.
+ */
+pub fn csv_rows(round: u16, heatmaps: &[Heatmap]) -> String {
+    let mut result = String::new();
+    for heatmap in heatmaps {
+        for y in 0..heatmap.height {
+            for x in 0..heatmap.width {
+                if let Some(value) = heatmap.get(Point::new(x, y)) {
+                    let _ = writeln!(result, "{},{},{},{},{}", round, heatmap.label(), x, y, value);
+                }
+            }
+        }
+    }
+    result
+}
+
+/**
+ * Red for winning moves, blue for losing ones and white in between,
+ * scaled so that the strongest score in any of the heatmaps is fully
+ * saturated. Cells without a value are grey.
+ */
+fn colour(value: Option<i32>, max_magnitude: i32) -> (u8, u8, u8) {
+    match value {
+        None => (128, 128, 128),
+        Some(v) => {
+            let strength = if max_magnitude == 0 { 0.0 } else { f64::from(v.abs()) / f64::from(max_magnitude) };
+            let faded = (255.0 * (1.0 - strength)).round() as u8;
+            if v >= 0 { (255, faded, faded) } else { (faded, faded, 255) }
+        }
+    }
+}
+
+fn max_magnitude(heatmaps: &[Heatmap]) -> i32 {
+    heatmaps.iter().flat_map(|h| h.values()).map(|v| v.abs()).max().unwrap_or(0)
+}
+
+fn image_size(heatmaps: &[Heatmap]) -> (usize, usize) {
+    let (width, height) = heatmaps.first().map(|h| (h.width as usize, h.height as usize)).unwrap_or((0, 0));
+    let panels = heatmaps.len();
+    (panels * width * CELL_PIXELS + panels.saturating_sub(1) * GAP_PIXELS, height * CELL_PIXELS)
+}
+
+/**
+ * Draws the heatmaps next to each other as an SVG image, with each
+ * building type labelled and each cell's score in its tooltip.
+ */
+pub fn to_svg(heatmaps: &[Heatmap]) -> String {
+    let max_magnitude = max_magnitude(heatmaps);
+    let (width, height) = image_size(heatmaps);
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" font-family="monospace" font-size="14">"#, width, height + LABEL_PIXELS);
+
+    for (i, heatmap) in heatmaps.iter().enumerate() {
+        let left = i * (heatmap.width as usize * CELL_PIXELS + GAP_PIXELS);
+        let _ = writeln!(svg, r#"<text x="{}" y="{}">{}</text>"#, left, LABEL_PIXELS - 6, heatmap.label());
+        for y in 0..heatmap.height {
+            for x in 0..heatmap.width {
+                let value = heatmap.get(Point::new(x, y));
+                let (r, g, b) = colour(value, max_magnitude);
+                let _ = write!(svg, r#"<rect x="{}" y="{}" width="{}" height="{}" fill="rgb({},{},{})" stroke="black" stroke-width="0.5">"#,
+                               left + x as usize * CELL_PIXELS, LABEL_PIXELS + y as usize * CELL_PIXELS, CELL_PIXELS, CELL_PIXELS, r, g, b);
+                match value {
+                    Some(v) => { let _ = write!(svg, "<title>{},{}: {}</title>", x, y, v); },
+                    None => { let _ = write!(svg, "<title>{},{}: not considered</title>", x, y); }
+                }
+                let _ = writeln!(svg, "</rect>");
+            }
+        }
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/**
+ * Draws the heatmaps next to each other as a binary PPM image, with a
+ * white gap between them.
+ */
+pub fn to_ppm(heatmaps: &[Heatmap]) -> Vec<u8> {
+    let max_magnitude = max_magnitude(heatmaps);
+    let (width, height) = image_size(heatmaps);
+    let panel_stride = heatmaps.first().map(|h| h.width as usize * CELL_PIXELS).unwrap_or(0) + GAP_PIXELS;
+    let mut ppm = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+
+    for pixel_y in 0..height {
+        for pixel_x in 0..width {
+            let panel = pixel_x / panel_stride;
+            let within_panel = pixel_x % panel_stride;
+            let (r, g, b) = match heatmaps.get(panel) {
+                Some(heatmap) if within_panel < heatmap.width as usize * CELL_PIXELS => {
+                    let point = Point::new((within_panel / CELL_PIXELS) as u8, (pixel_y / CELL_PIXELS) as u8);
+                    colour(heatmap.get(point), max_magnitude)
+                },
+                _ => (255, 255, 255)
+            };
+            ppm.extend_from_slice(&[r, g, b]);
+        }
+    }
+    ppm
+}
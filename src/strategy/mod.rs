@@ -1,3 +1,49 @@
+use engine::command::Command;
+use engine::bitwise_engine::BitwiseGameState;
+use engine::bitboard::MapSize;
+use engine::constants::INIT_SEED;
+
+use time::{Duration, PreciseTime};
+
+pub mod budget;
+pub mod config;
+pub mod decision_log;
+pub mod heatmap;
 pub mod monte_carlo;
 pub mod monte_carlo_tree;
 pub mod static_opening;
+pub mod random;
+
+/**
+ * A way of choosing the bot's next move.
+ *
+ * The state is always from the bot's point of view, with the bot as
+ * `state.player`. Strategies are kept for the whole game, so they can
+ * carry things over from one round to the next.
+ */
+pub trait Strategy<M: MapSize> {
+    fn choose_move(&mut self, state: &BitwiseGameState<M>, start_time: PreciseTime, max_time: Duration) -> Command;
+
+    /**
+     * Chooses a move for `state.opponent` instead, by looking at the
+     * state from their side. Anything the strategy carries over
+     * between rounds assumes it's always on the same side, so an
+     * instance should only be used for one of the players.
+     */
+    fn choose_opponent_move(&mut self, state: &BitwiseGameState<M>, start_time: PreciseTime, max_time: Duration) -> Command {
+        self.choose_move(&state.swapped(), start_time, max_time)
+    }
+}
+
+/**
+ * Spreads a number over the bytes of an rng seed. Zero gives
+ * `INIT_SEED`, which is what the bot uses unless told otherwise.
+ */
+pub fn seed_from_u64(number: u64) -> [u8; 16] {
+    let mut seed = INIT_SEED;
+    for (i, byte) in number.to_le_bytes().iter().enumerate() {
+        seed[i] ^= byte;
+        seed[i + 8] ^= byte.rotate_left(3);
+    }
+    seed
+}
@@ -2,27 +2,124 @@ use engine::command::*;
 use engine::status::GameStatus;
 use engine::bitwise_engine::{Player, BitwiseGameState};
 use engine::constants::*;
+use engine::config::GameConfig;
+use engine::bitboard::MapSize;
+use engine::bitboard::{Bitboard, MAX_MAP_POSITIONS, MAX_MAP_HEIGHT};
 use engine::geometry::*;
+use strategy::Strategy;
+use strategy::budget::{SearchBudget, SearchProgress};
+use strategy::decision_log::{DecisionRecord, CandidateRecord};
+#[cfg(feature = "debug-decisions")]
+use strategy::heatmap;
+use input;
+use input::command::format_replay_command;
+use replay::recorder::{self, DECISION_FILE};
 
+use std::error::Error;
 use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
 
 use rand::{Rng, XorShiftRng, SeedableRng};
 
-use arrayvec::ArrayVec;
-
 use time::{Duration, PreciseTime};
 
 #[cfg(not(feature = "single-threaded"))]
 use rayon::prelude::*;
 
-#[cfg(feature = "energy-cutoff")] pub const ENERGY_PRODUCTION_CUTOFF: u16 = 50;
-#[cfg(feature = "energy-cutoff")] pub const ENERGY_STORAGE_CUTOFF: u16 = 120;
+/**
+ * Options for how random moves are chosen in the simulated games.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RandomMoveOptions {
+    /// Weight the moves with some hand tuned heuristics, instead of
+    /// choosing uniformly.
+    pub heuristic: bool,
+    /// Don't build more energy towers once there is plenty of
+    /// energy. The heuristic moves always do this.
+    pub energy_cutoff: bool
+}
+
+impl Default for RandomMoveOptions {
+    fn default() -> RandomMoveOptions {
+        RandomMoveOptions {
+            heuristic: false,
+            energy_cutoff: true
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonteCarloOptions {
+    /// Spend the later part of the time only on the options that
+    /// did well early on.
+    pub discard_poor_performers: bool,
+    /// Weight wins and losses by the difference in towers left at
+    /// the end of the game.
+    pub weighted_win_ratio: bool,
+    pub random_moves: RandomMoveOptions
+}
+
+impl Default for MonteCarloOptions {
+    fn default() -> MonteCarloOptions {
+        MonteCarloOptions {
+            discard_poor_performers: true,
+            weighted_win_ratio: true,
+            random_moves: RandomMoveOptions::default()
+        }
+    }
+}
 
-pub fn choose_move(state: &BitwiseGameState, start_time: PreciseTime, max_time: Duration) -> Command {
-    let mut command_scores = CommandScore::init_command_scores(state);
+/**
+ * Plays random games starting with each possible move, and picks the
+ * move that wins the most of them.
+ *
+ * If `decision_log` is set, the scores for every round are written to
+ * `Round NNN/decision.json` in that folder. If `budget` is set, it's
+ * used instead of the time limit for the turn.
+ */
+#[derive(Debug, Clone)]
+pub struct MonteCarlo {
+    pub options: MonteCarloOptions,
+    pub seed: [u8; 16],
+    pub budget: Option<SearchBudget>,
+    pub decision_log: Option<PathBuf>
+}
+
+impl Default for MonteCarlo {
+    fn default() -> MonteCarlo {
+        MonteCarlo {
+            options: MonteCarloOptions::default(),
+            seed: INIT_SEED,
+            budget: None,
+            decision_log: None
+        }
+    }
+}
+
+impl<M: MapSize> Strategy<M> for MonteCarlo {
+    fn choose_move(&mut self, state: &BitwiseGameState<M>, start_time: PreciseTime, max_time: Duration) -> Command {
+        let budget = SearchBudget::or_time(self.budget, max_time);
+        let (command, command_scores) = search(state, start_time, budget, self.seed, &self.options);
+        if let Some(ref folder) = self.decision_log {
+            let record = decision_record(state.round, &command_scores, command, &self.options);
+            if let Err(error) = write_decision_record(folder, &record) {
+                println!("Error while writing the decision log: {}", error);
+            }
+        }
+        command
+    }
+}
+
+pub fn choose_move<M: MapSize>(state: &BitwiseGameState<M>, start_time: PreciseTime, max_time: Duration, options: &MonteCarloOptions) -> Command {
+    search(state, start_time, SearchBudget::or_time(None, max_time), INIT_SEED, options).0
+}
+
+fn search<M: MapSize>(state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, seed: [u8; 16], options: &MonteCarloOptions) -> (Command, Vec<CommandScore>) {
+    let mut command_scores = CommandScore::init_command_scores(state, seed);
 
     let command = {
-        let best_command_score = simulate_options_to_timeout(&mut command_scores, state, start_time, max_time);
+        let best_command_score = simulate_options_to_budget(&mut command_scores, state, start_time, budget, options);
         match best_command_score {
             Some(best) if !best.starts_with_nothing => best.command,
             _ => Command::Nothing
@@ -36,121 +133,172 @@ pub fn choose_move(state: &BitwiseGameState, start_time: PreciseTime, max_time:
     }
     #[cfg(feature = "debug-decisions")]
     {
-        debug_print_choices("ENERGY", &command_scores, |score| match score.command {
-            Command::Build(p, BuildingType::Energy) => Some((p, score.win_ratio())),
-            _ => None
-        });
-        debug_print_choices("ATTACK", &command_scores, |score| match score.command {
-            Command::Build(p, BuildingType::Attack) => Some((p, score.win_ratio())),
-            _ => None
-        });
-        debug_print_choices("DEFENCE", &command_scores, |score| match score.command {
-            Command::Build(p, BuildingType::Defence) => Some((p, score.win_ratio())),
-            _ => None
-        });
-        debug_print_choices("TESLA", &command_scores, |score| match score.command {
-            Command::Build(p, BuildingType::Tesla) => Some((p, score.win_ratio())),
-            _ => None
-        });
+        let weighted = options.weighted_win_ratio;
+        let heatmaps = heatmap::building_heatmaps::<M, _>(command_scores.iter().map(|score| (score.command, score.win_ratio(weighted))));
+        for heatmap in &heatmaps {
+            debug_print_choices(heatmap);
+        }
         
         println!("NOTHING");
-        println!("{}", command_scores.iter().find(|c| c.command == Command::Nothing).map(|s| s.win_ratio()).unwrap_or(0));
+        println!("{}", command_scores.iter().find(|c| c.command == Command::Nothing).map(|s| s.win_ratio(weighted)).unwrap_or(0));
         println!();
 
         println!("IRON CURTAIN");
-        println!("{}", command_scores.iter().find(|c| c.command == Command::IronCurtain).map(|s| s.win_ratio()).unwrap_or(0));
+        println!("{}", command_scores.iter().find(|c| c.command == Command::IronCurtain).map(|s| s.win_ratio(weighted)).unwrap_or(0));
         println!();
     }
 
-    command
+    (command, command_scores)
+}
+
+/**
+ * Describes the search once it's finished. The scores are still in the
+ * order that discarding poor performers left them, so the candidates
+ * dropped in each phase can be read off from the phase lengths.
+ */
+fn decision_record(round: u16, command_scores: &[CommandScore], choice: Command, options: &MonteCarloOptions) -> DecisionRecord {
+    let pruned = if options.discard_poor_performers {
+        let lengths = discard_phase_lengths(command_scores.len());
+        lengths.windows(2)
+            .map(|phase| command_scores[phase[1]..phase[0]].iter().map(|c| format_replay_command(c.command)).collect())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    DecisionRecord {
+        round,
+        weighted_win_ratio: options.weighted_win_ratio,
+        candidates: command_scores.iter().map(|c| c.to_record(options.weighted_win_ratio)).collect(),
+        pruned,
+        choice: format_replay_command(choice)
+    }
+}
+
+fn write_decision_record(folder: &Path, record: &DecisionRecord) -> Result<(), Box<dyn Error>> {
+    let round_folder = recorder::round_folder(folder, record.round);
+    fs::create_dir_all(&round_folder)?;
+    input::json::write_decision_record_to_file(record, &round_folder.join(DECISION_FILE).to_string_lossy())
 }
 
 #[cfg(feature = "debug-decisions")]
-fn debug_print_choices<F: FnMut(&CommandScore) -> Option<(Point, i32)>>(label: &str, command_scores: &[CommandScore], extractor: F) {
-    println!("#+NAME: {}", label);
+fn debug_print_choices(heatmap: &heatmap::Heatmap) {
+    println!("#+NAME: {}", heatmap.label());
     println!("#+PLOT: type:3d with:pm3d");
-    let relevant_moves: Vec<(Point, i32)>  = command_scores.iter()
-        .filter_map(extractor)
-        .collect();
-    for y in 0..MAP_HEIGHT {
-        for x in 0..SINGLE_MAP_WIDTH {
-            let point = Point::new(x, y);
-            let score = relevant_moves.iter().find(|(p, _)| *p == point);
-            print!(" | {}", score.map(|(_,s)| s).cloned().unwrap_or(0));
+    for y in 0..heatmap.height {
+        for x in 0..heatmap.width {
+            print!(" | {}", heatmap.get(Point::new(x, y)).unwrap_or(0));
         }
         println!(" |");
     }
     println!();
 }
 
-#[cfg(not(feature = "discard-poor-performers"))]
-fn simulate_options_to_timeout<'a>(command_scores: &'a mut Vec<CommandScore>, state: &BitwiseGameState, start_time: PreciseTime, max_time: Duration) -> Option<&'a CommandScore> {
+fn simulate_options_to_budget<'a, M: MapSize>(command_scores: &'a mut [CommandScore], state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, options: &MonteCarloOptions) -> Option<&'a CommandScore> {
+    if options.discard_poor_performers {
+        simulate_options_discarding_poor_performers(command_scores, state, start_time, budget, options)
+    } else {
+        simulate_all_options_to_budget(command_scores, state, start_time, budget, options)
+    }
+}
+
+fn simulate_all_options_to_budget<'a, M: MapSize>(command_scores: &'a mut [CommandScore], state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, options: &MonteCarloOptions) -> Option<&'a CommandScore> {
+    let mut progress = SearchProgress::default();
     loop {
-        simulate_all_options_once(command_scores, state);
-        if start_time.to(PreciseTime::now()) > max_time {
+        simulate_all_options_once(command_scores, state, options);
+        record_progress(&mut progress, command_scores.len());
+        if budget.spent(start_time, &progress) {
             break;
         }
     }
-    command_scores.iter().max_by_key(|&c| c.win_ratio())
+    command_scores.iter().max_by_key(|&c| c.win_ratio(options.weighted_win_ratio))
 }
 
-#[cfg(feature = "discard-poor-performers")]
-fn simulate_options_to_timeout<'a>(command_scores: &'a mut Vec<CommandScore>, state: &BitwiseGameState, start_time: PreciseTime, max_time: Duration) -> Option<&'a CommandScore> {
-    use std::cmp;
-    let min_options = cmp::min(command_scores.len(), 5);
-    
-    let maxes = [max_time / 3, max_time * 2 / 3, max_time];
-    for (i, &max) in maxes.iter().enumerate() {
-        let new_length = cmp::max(min_options, command_scores.len() / (2usize.pow(i as u32)));
+fn simulate_options_discarding_poor_performers<'a, M: MapSize>(command_scores: &'a mut [CommandScore], state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, options: &MonteCarloOptions) -> Option<&'a CommandScore> {
+    let maxes = [budget.fraction(1, 3), budget.fraction(2, 3), budget];
+    let lengths = discard_phase_lengths(command_scores.len());
+    let mut progress = SearchProgress::default();
+    for (&max, &new_length) in maxes.iter().zip(lengths.iter()) {
         let active_scores = &mut command_scores[0..new_length];
         loop {
-            simulate_all_options_once(active_scores, state);
-            if start_time.to(PreciseTime::now()) > max {
+            simulate_all_options_once(active_scores, state, options);
+            record_progress(&mut progress, active_scores.len());
+            if max.spent(start_time, &progress) {
                 break;
             }
         }
-        active_scores.sort_unstable_by_key(|c| -c.win_ratio());
+        active_scores.sort_unstable_by_key(|c| -c.win_ratio(options.weighted_win_ratio));
     }
     command_scores.first()
 }
 
+/**
+ * Each option still being simulated has just played one more game.
+ * There's no tree, so every game counts as a node.
+ */
+fn record_progress(progress: &mut SearchProgress, active_options: usize) {
+    progress.rollouts += active_options as u32;
+    progress.rollouts_per_candidate += 1;
+    progress.nodes = progress.rollouts;
+}
+
+/**
+ * How many of the options are still being simulated in each phase of
+ * discarding poor performers. Each phase keeps the better half.
+ */
+fn discard_phase_lengths(option_count: usize) -> [usize; 3] {
+    use std::cmp;
+    let min_options = cmp::min(option_count, 5);
+    let mut lengths = [0; 3];
+    for (i, length) in lengths.iter_mut().enumerate() {
+        *length = cmp::max(min_options, option_count / (2usize.pow(i as u32)));
+    }
+    lengths
+}
+
 #[cfg(feature = "single-threaded")]
-fn simulate_all_options_once(command_scores: &mut[CommandScore], state: &BitwiseGameState) {
+fn simulate_all_options_once<M: MapSize>(command_scores: &mut[CommandScore], state: &BitwiseGameState<M>, options: &MonteCarloOptions) {
     command_scores.iter_mut()
         .for_each(|score| {
             let mut rng = XorShiftRng::from_seed(score.next_seed);
-            simulate_to_endstate(score, state, &mut rng);
+            simulate_to_endstate(score, state, &options.random_moves, &mut rng);
         });
 }
 
 #[cfg(not(feature = "single-threaded"))]
-fn simulate_all_options_once(command_scores: &mut[CommandScore], state: &BitwiseGameState) {
+fn simulate_all_options_once<M: MapSize>(command_scores: &mut[CommandScore], state: &BitwiseGameState<M>, options: &MonteCarloOptions) {
     command_scores.par_iter_mut()
         .for_each(|score| {
             let mut rng = XorShiftRng::from_seed(score.next_seed);
-            simulate_to_endstate(score, state, &mut rng);
+            simulate_to_endstate(score, state, &options.random_moves, &mut rng);
         });
 }
 
-fn simulate_to_endstate<R: Rng>(command_score: &mut CommandScore, state: &BitwiseGameState, rng: &mut R) {
+/**
+ * Plays a game out from `state`, starting with the score's command.
+ * The game is played on a copy of the state rather than undone with
+ * `unsimulate` afterwards: an undo record keeps as much as a copy
+ * does, and a game needs one for every round.
+ */
+fn simulate_to_endstate<M: MapSize, R: Rng>(command_score: &mut CommandScore, state: &BitwiseGameState<M>, random_moves: &RandomMoveOptions, rng: &mut R) {
     let mut state_mut = state.clone();
     
     let mut status = GameStatus::Continue; //state_mut.simulate(command_score.command, opponent_first);
     let mut first_move_made = false;
     
-    for _ in 0..MAX_MOVES {
+    for _ in 0..state.config.max_rounds {
         if status != GameStatus::Continue {
             break;
         }
 
         let player_command = if first_move_made {
-            random_move(&state_mut.player, &state_mut.opponent, rng)
+            random_move(&state_mut.player, &state_mut.opponent, &state_mut.config, random_moves, rng)
         } else {
-            let do_nothing = command_score.command.cant_build_yet(state_mut.player.energy);
+            let do_nothing = command_score.command.cant_build_yet(state_mut.player.energy, &state_mut.config);
             first_move_made = !do_nothing;
             if do_nothing { Command::Nothing } else { command_score.command }
         };
-        let opponent_command = random_move(&state_mut.opponent, &state_mut.player, rng);
+        let opponent_command = random_move(&state_mut.opponent, &state_mut.player, &state_mut.config, random_moves, rng);
         status = state_mut.simulate(player_command, opponent_command);
     }
 
@@ -164,33 +312,27 @@ fn simulate_to_endstate<R: Rng>(command_score: &mut CommandScore, state: &Bitwis
     }
 }
 
-#[cfg(feature = "heuristic-random")]
-pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> Command {
-    lazy_static! {
-        static ref MOVES: [Command; NUMBER_OF_POSSIBLE_MOVES] = {
-            let mut m = [Command::Nothing; NUMBER_OF_POSSIBLE_MOVES];
-            m[1] = Command::IronCurtain;
-            let mut i = 2;
-            for b in &[BuildingType::Energy, BuildingType::Defence, BuildingType::Attack, BuildingType::Tesla] {
-                for p in 0..NUMBER_OF_MAP_POSITIONS as u8 {
-                let point = Point::new_index(p);
-                    m[i] = Command::Build(point, *b);
-                    i += 1;
-                }
-            }
-            m
-        };
+pub fn random_move<M: MapSize, R: Rng>(player: &Player<M>, opponent: &Player<M>, config: &GameConfig, options: &RandomMoveOptions, rng: &mut R) -> Command {
+    if options.heuristic {
+        heuristic_random_move(player, opponent, config, rng)
+    } else {
+        uniform_random_move(player, config, options, rng)
     }
+}
+
+fn heuristic_random_move<M: MapSize, R: Rng>(player: &Player<M>, opponent: &Player<M>, config: &GameConfig, rng: &mut R) -> Command {
+    let positions = M::NUMBER_OF_MAP_POSITIONS;
 
     let mut cdf_other = [0; 2];
-    let mut cdf_energy = [0; NUMBER_OF_MAP_POSITIONS];
-    let mut cdf_defence = [0; NUMBER_OF_MAP_POSITIONS];
-    let mut cdf_attack = [0; NUMBER_OF_MAP_POSITIONS];
-    let mut cdf_tesla = [0; NUMBER_OF_MAP_POSITIONS];
-
-    let mut attack_metric_per_row = [0; MAP_HEIGHT as usize];
-    let mut defence_metric_per_row = [0; MAP_HEIGHT as usize];
-    for y in 0..MAP_HEIGHT {
+    let mut cdf_energy = [0; MAX_MAP_POSITIONS];
+    let mut cdf_defence = [0; MAX_MAP_POSITIONS];
+    let mut cdf_attack = [0; MAX_MAP_POSITIONS];
+    let mut cdf_tesla = [0; MAX_MAP_POSITIONS];
+    let mut cdf_deconstruct = [0; MAX_MAP_POSITIONS];
+
+    let mut attack_metric_per_row = [0; MAX_MAP_HEIGHT];
+    let mut defence_metric_per_row = [0; MAX_MAP_HEIGHT];
+    for y in 0..M::MAP_HEIGHT {
         let opponent_energy = opponent.count_energy_towers_in_row(y);
         let opponent_attack = opponent.count_attack_towers_in_row(y);
         let opponent_towers = opponent.count_towers_in_row(y);
@@ -200,14 +342,16 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
         let player_towers = player.count_towers_in_row(y);
 
         defence_metric_per_row[y as usize] = if opponent_attack == 0 { 0 } else { opponent_attack + player_towers };
-        attack_metric_per_row[y as usize] = 8 + opponent_energy + opponent_towers + player_energy - player_attack;
+        attack_metric_per_row[y as usize] = u16::from(M::SINGLE_MAP_WIDTH) + opponent_energy + opponent_towers + player_energy - player_attack;
     }
     
 
+    let legal = player.legal_commands(config);
+
     let mut other_end: u16 = 0;
     // Nothing
     {
-        let weight = if player.can_build_iron_curtain() && player.energy < IRON_CURTAIN_PRICE {
+        let weight = if player.can_build_iron_curtain() && player.energy < config.iron_curtain_price {
             5
         } else {
             0
@@ -218,7 +362,7 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
     
     // Iron Curtain
     {
-        let weight = if player.can_build_iron_curtain() && player.energy >= IRON_CURTAIN_PRICE {
+        let weight = if legal.iron_curtain {
             50
         } else {
             0
@@ -229,12 +373,11 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
 
     // Energy
     let mut energy_end: u16 = other_end;
-    let needs_energy = player.energy_generated() <= ENERGY_PRODUCTION_CUTOFF ||
-        player.energy <= ENERGY_STORAGE_CUTOFF;
-    if needs_energy && player.energy >= ENERGY_PRICE {
-        for p in 0..NUMBER_OF_MAP_POSITIONS as u8 {
-            let point = Point::new_index(p);
-            let weight = if player.occupied & point.to_either_bitfield() != 0 {
+    let needs_energy = player.needs_energy(config);
+    if needs_energy && legal.can_build(BuildingType::Energy) {
+        for p in 0..positions as u8 {
+            let point = Point::from_bit_index::<M>(p);
+            let weight = if player.occupied & point.to_either_bitfield::<M>() != M::Bits::ZERO {
                 0
             } else {
                 2
@@ -247,12 +390,12 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
 
     // Defence
     let mut defence_end: u16 = energy_end;
-    if player.energy >= DEFENCE_PRICE {
-        for p in 0..NUMBER_OF_MAP_POSITIONS as u8 {
-            let point = Point::new_index(p);
+    if legal.can_build(BuildingType::Defence) {
+        for p in 0..positions as u8 {
+            let point = Point::from_bit_index::<M>(p);
             let y = usize::from(point.y());
 
-            let weight = if player.occupied & point.to_either_bitfield() != 0 || point.x() < 4 {
+            let weight = if player.occupied & point.to_either_bitfield::<M>() != M::Bits::ZERO || point.x() < M::SINGLE_MAP_WIDTH / 2 {
                 0
             } else {
                 defence_metric_per_row[y]
@@ -265,10 +408,10 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
 
     // Attack
     let mut attack_end: u16 = defence_end;
-    if player.energy >= MISSILE_PRICE {
-        for p in 0..NUMBER_OF_MAP_POSITIONS as u8 {
-            let point = Point::new_index(p);
-            let weight = if player.occupied & point.to_either_bitfield() != 0 {
+    if legal.can_build(BuildingType::Attack) {
+        for p in 0..positions as u8 {
+            let point = Point::from_bit_index::<M>(p);
+            let weight = if player.occupied & point.to_either_bitfield::<M>() != M::Bits::ZERO {
                 0
             } else {
                 let y = usize::from(point.y());
@@ -282,11 +425,10 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
 
     // Tesla
     let mut tesla_end: u16 = attack_end;
-    let cant_tesla = player.has_max_teslas() || player.energy < TESLA_PRICE;
-    if !cant_tesla {
-        for p in 0..NUMBER_OF_MAP_POSITIONS as u8 {
-            let point = Point::new_index(p);
-            let weight = if (player.occupied & point.to_either_bitfield() != 0) || point.y() < 7 {
+    if legal.can_build(BuildingType::Tesla) {
+        for p in 0..positions as u8 {
+            let point = Point::from_bit_index::<M>(p);
+            let weight = if (player.occupied & point.to_either_bitfield::<M>() != M::Bits::ZERO) || point.y() < M::MAP_HEIGHT - 1 {
                 0
             } else {
                 10
@@ -297,7 +439,23 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
         }
     }
 
-    let cumulative_distribution = tesla_end;
+    // Deconstruct
+    let mut deconstruct_end: u16 = tesla_end;
+    if !needs_energy {
+        for p in 0..positions as u8 {
+            let point = Point::from_bit_index::<M>(p);
+            let weight = if player.energy_towers & point.to_either_bitfield::<M>() != M::Bits::ZERO {
+                1
+            } else {
+                0
+            };
+
+            deconstruct_end += weight;
+            cdf_deconstruct[p as usize] = deconstruct_end;
+        }
+    }
+
+    let cumulative_distribution = deconstruct_end;
 
     if cumulative_distribution == 0 {
         return Command::Nothing;
@@ -305,39 +463,42 @@ pub fn random_move<R: Rng>(player: &Player, opponent: &Player, rng: &mut R) -> C
 
     let choice = rng.gen_range(0, cumulative_distribution);
 
-    let index = match choice {
-        c if c < other_end => cdf_other.iter().position(|&c| c > choice).expect("Random number has exceeded cumulative distribution"),
-        c if c < energy_end => 2 + cdf_energy.iter().position(|&c| c > choice).expect("Random number has exceeded cumulative distribution"),
-        c if c < defence_end => 2 + NUMBER_OF_MAP_POSITIONS + cdf_defence.iter().position(|&c| c > choice).expect("Random number has exceeded cumulative distribution"),
-        c if c < attack_end => 2 + 2 * NUMBER_OF_MAP_POSITIONS + cdf_attack.iter().position(|&c| c > choice).expect("Random number has exceeded cumulative distribution"),
-        _ => 2 + 3 * NUMBER_OF_MAP_POSITIONS + cdf_tesla.iter().position(|&c| c > choice).expect("Random number has exceeded cumulative distribution"),
+    let chosen_point = |cdf: &[u16]| {
+        let index = cdf[..positions].iter().position(|&c| c > choice).expect("Random number has exceeded cumulative distribution");
+        Point::from_bit_index::<M>(index as u8)
     };
 
-    MOVES[index]
+    match choice {
+        c if c < other_end => if cdf_other[0] > choice { Command::Nothing } else { Command::IronCurtain },
+        c if c < energy_end => Command::Build(chosen_point(&cdf_energy), BuildingType::Energy),
+        c if c < defence_end => Command::Build(chosen_point(&cdf_defence), BuildingType::Defence),
+        c if c < attack_end => Command::Build(chosen_point(&cdf_attack), BuildingType::Attack),
+        c if c < tesla_end => Command::Build(chosen_point(&cdf_tesla), BuildingType::Tesla),
+        _ => Command::Deconstruct(chosen_point(&cdf_deconstruct)),
+    }
 }
 
-#[cfg(not(feature = "heuristic-random"))]
-pub fn random_move<R: Rng>(player: &Player, _opponent: &Player, rng: &mut R) -> Command {
-    let free_positions_count = player.unoccupied_cell_count();
+fn uniform_random_move<M: MapSize, R: Rng>(player: &Player<M>, config: &GameConfig, options: &RandomMoveOptions, rng: &mut R) -> Command {
+    let commands = player.sensible_commands(config, options.energy_cutoff);
 
-    let open_building_spot = free_positions_count > 0;
-
-    let all_buildings = sensible_buildings(player, open_building_spot);
-
-    let iron_curtain_count = if player.can_build_iron_curtain() && player.energy >= IRON_CURTAIN_PRICE { 1 } else { 0 };
+    let iron_curtain_count = if commands.iron_curtain { 1 } else { 0 };
     let nothing_count = 1;
+    let deconstruct_count = if commands.deconstruct_cells != M::Bits::ZERO { 1 } else { 0 };
 
-    let building_choice_index = rng.gen_range(0, all_buildings.len() + nothing_count + iron_curtain_count);
+    let building_choice_index = rng.gen_range(0, commands.buildings.len() + nothing_count + iron_curtain_count + deconstruct_count);
 
-    if building_choice_index < all_buildings.len() {
-        let position_choice = rng.gen_range(0, free_positions_count);
+    if building_choice_index < commands.buildings.len() {
+        let position_choice = rng.gen_range(0, commands.build_cell_count());
         Command::Build(
-            player.location_of_unoccupied_cell(position_choice),
-            all_buildings[building_choice_index]
+            commands.build_cell(position_choice),
+            commands.buildings[building_choice_index]
         )
     }
-    else if building_choice_index == all_buildings.len() {
+    else if building_choice_index == commands.buildings.len() {
         Command::Nothing        
+    } else if building_choice_index == commands.buildings.len() + nothing_count + iron_curtain_count {
+        let position_choice = rng.gen_range(0, commands.deconstruct_cell_count());
+        Command::Deconstruct(commands.deconstruct_cell(position_choice))
     } else {
         Command::IronCurtain
     }
@@ -358,7 +519,7 @@ struct CommandScore {
 }
 
 impl CommandScore {
-    fn new(command: Command, starts_with_nothing: bool) -> CommandScore {
+    fn new(command: Command, starts_with_nothing: bool, seed: [u8; 16]) -> CommandScore {
         CommandScore {
             command, starts_with_nothing,
             victory_score: 0,
@@ -368,7 +529,7 @@ impl CommandScore {
             draws: 0,
             stalemates: 0,
             attempts: 0,
-            next_seed: INIT_SEED
+            next_seed: seed
         }
     }
 
@@ -400,49 +561,62 @@ impl CommandScore {
         self.next_seed = next_seed;
     }
 
-    #[cfg(feature = "weighted-win-ratio")]
-    fn win_ratio(&self) -> i32 {
-        (self.victory_score - self.defeat_score) * 10000 / (self.attempts as i32)
+    fn win_ratio(&self, weighted: bool) -> i32 {
+        if weighted {
+            (self.victory_score - self.defeat_score) * 10000 / (self.attempts as i32)
+        } else {
+            (self.victories as i32 - self.defeats as i32) * 10000 / (self.attempts as i32)
+        }
     }
 
-    #[cfg(not(feature = "weighted-win-ratio"))]
-    fn win_ratio(&self) -> i32 {
-        (self.victories as i32 - self.defeats as i32) * 10000 / (self.attempts as i32)
+    fn to_record(&self, weighted: bool) -> CandidateRecord {
+        CandidateRecord {
+            command: format_replay_command(self.command),
+            starts_with_nothing: self.starts_with_nothing,
+            attempts: self.attempts,
+            victories: self.victories,
+            defeats: self.defeats,
+            draws: self.draws,
+            stalemates: self.stalemates,
+            victory_score: self.victory_score,
+            defeat_score: self.defeat_score,
+            win_ratio: self.win_ratio(weighted)
+        }
     }
 
-    fn init_command_scores(state: &BitwiseGameState) -> Vec<CommandScore> {
-        let unoccupied_cells_count = state.player.unoccupied_cell_count();
-        let unoccupied_cells = (0..unoccupied_cells_count)
-            .map(|i| state.player.location_of_unoccupied_cell(i));
-        let energy_generated = state.player.energy_generated();
+    fn init_command_scores<M: MapSize>(state: &BitwiseGameState<M>, seed: [u8; 16]) -> Vec<CommandScore> {
+        let player = &state.player;
+        let config = &state.config;
+        let energy_generated = player.energy_generated(config);
 
-        let mut all_buildings: ArrayVec<[BuildingType; NUMBER_OF_BUILDING_TYPES]> = ArrayVec::new();
-        if DEFENCE_PRICE <= state.player.energy {
-            all_buildings.push(BuildingType::Defence);
-        }
-        if MISSILE_PRICE <= state.player.energy {
-            all_buildings.push(BuildingType::Attack);
+        let mut candidates = player.sensible_commands(config, false);
+        // Teslas and the iron curtain are expensive enough that they're
+        // worth considering a few rounds before they can be afforded.
+        // Until then, the move does nothing.
+        let tesla_within_reach = config.tesla_price.saturating_sub(player.energy) / energy_generated < 4;
+        if candidates.build_cells != M::Bits::ZERO && !candidates.can_build(BuildingType::Tesla) && !player.has_max_teslas() && tesla_within_reach {
+            candidates.buildings.push(BuildingType::Tesla);
         }
-        if ENERGY_PRICE <= state.player.energy {
-            all_buildings.push(BuildingType::Energy);
-        }
-        if !state.player.has_max_teslas() && (TESLA_PRICE.saturating_sub(state.player.energy) / energy_generated < 4) {
-            all_buildings.push(BuildingType::Tesla);
-        }
-        
-        let building_command_count = unoccupied_cells.len()*all_buildings.len();
 
-        let mut commands = Vec::with_capacity(building_command_count + 1);
-        let time_to_curtain_energy = (IRON_CURTAIN_PRICE.saturating_sub(state.player.energy) / energy_generated) as u8;
+        let mut commands = Vec::with_capacity(candidates.count() + 1);
+        let time_to_curtain_energy = (config.iron_curtain_price.saturating_sub(player.energy) / energy_generated) as u8;
         
-        if time_to_curtain_energy < 4 && state.player.can_build_iron_curtain_in(state.round, time_to_curtain_energy) {
-            commands.push(CommandScore::new(Command::IronCurtain, state.player.energy < IRON_CURTAIN_PRICE));
+        if time_to_curtain_energy < 4 && player.can_build_iron_curtain_in(state.round, time_to_curtain_energy, config) {
+            commands.push(CommandScore::new(Command::IronCurtain, player.energy < config.iron_curtain_price, seed));
         }
 
-        for position in unoccupied_cells {
-            for &building in &all_buildings {
-                commands.push(CommandScore::new(Command::Build(position, building), building.cant_build_yet(state.player.energy)));
-            }
+        for command in candidates.builds() {
+            commands.push(CommandScore::new(command, command.cant_build_yet(player.energy, config), seed));
+        }
+
+        // Doing nothing has to be an option alongside deconstructing,
+        // otherwise a deconstruct would be chosen just for being the
+        // only command on the list.
+        if candidates.deconstruct_cells != M::Bits::ZERO {
+            commands.push(CommandScore::new(Command::Nothing, false, seed));
+        }
+        for command in candidates.deconstructs() {
+            commands.push(CommandScore::new(command, false, seed));
         }
 
         commands
@@ -451,56 +625,6 @@ impl CommandScore {
 
 impl fmt::Display for CommandScore {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{},{}", self.command, self.win_ratio())
-    }
-}
-
-#[cfg(all(not(feature = "heuristic-random"), not(feature = "energy-cutoff")))]
-fn sensible_buildings(player: &Player, open_building_spot: bool) -> ArrayVec<[BuildingType; NUMBER_OF_BUILDING_TYPES]> {
-    let mut result = ArrayVec::new();
-    if !open_building_spot {
-        return result;
-    }
-
-    if DEFENCE_PRICE <= player.energy {
-        result.push(BuildingType::Defence);
-    }
-    if MISSILE_PRICE <= player.energy {
-        result.push(BuildingType::Attack);
-    }
-    if ENERGY_PRICE <= player.energy {
-        result.push(BuildingType::Energy);
-    }
-    if TESLA_PRICE <= player.energy && !player.has_max_teslas() {
-        result.push(BuildingType::Tesla);
+        write!(f, "{},{}", self.command, self.win_ratio(MonteCarloOptions::default().weighted_win_ratio))
     }
-
-    result
-}
-
-#[cfg(all(not(feature = "heuristic-random"), feature = "energy-cutoff"))]
-fn sensible_buildings(player: &Player, open_building_spot: bool) -> ArrayVec<[BuildingType; NUMBER_OF_BUILDING_TYPES]> {
-    let mut result = ArrayVec::new();
-    if !open_building_spot {
-        return result;
-    }
-
-    let needs_energy = player.energy_generated() <= ENERGY_PRODUCTION_CUTOFF ||
-        player.energy <= ENERGY_STORAGE_CUTOFF;
-
-    if DEFENCE_PRICE <= player.energy {
-        result.push(BuildingType::Defence);
-    }
-    if MISSILE_PRICE <= player.energy {
-        result.push(BuildingType::Attack);
-    }
-    if ENERGY_PRICE <= player.energy && needs_energy {
-        result.push(BuildingType::Energy);
-    }
-    if TESLA_PRICE <= player.energy && !player.has_max_teslas() {
-        result.push(BuildingType::Tesla);
-    }
-    
-    result
 }
-
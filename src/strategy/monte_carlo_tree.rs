@@ -2,242 +2,887 @@ use engine::command::*;
 use engine::status::GameStatus;
 use engine::bitwise_engine::{Player, BitwiseGameState};
 use engine::constants::*;
+use engine::config::GameConfig;
+use engine::bitboard::MapSize;
+use engine::geometry::Point;
+use input;
 
 use rand::{Rng, XorShiftRng, SeedableRng};
 use time::{Duration, PreciseTime};
 
-use strategy::monte_carlo;
+use strategy::Strategy;
+use strategy::budget::{SearchBudget, SearchProgress};
+use strategy::monte_carlo::{self, RandomMoveOptions};
 
-use arrayvec::ArrayVec;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
 
-#[derive(Debug)]
-struct NodeStats {
+#[cfg(not(feature = "single-threaded"))]
+use rayon::prelude::*;
+
+/// The number of trees searched when the budget counts work rather
+/// than time. It's fixed so that the moves don't depend on how many
+/// threads there are.
+pub const DEFAULT_TREE_COUNT: usize = 8;
+
+/// The number of nodes kept in memory, shared between the trees, if
+/// the strategy doesn't set its own limit.
+pub const DEFAULT_MAX_NODES: usize = 100_000;
+
+/// Where the tree is saved between turns, in the decision folder.
+pub const TREE_FILE: &str = "tree.bin";
+
+const TREE_FILE_MAGIC: &[u8; 8] = b"ZTREE003";
+
+type NodeId = u32;
+
+/// The root is always the first node in its tree's pool.
+const ROOT: NodeId = 0;
+
+/**
+ * The scores of one player's move from a node in the tree, from that
+ * player's point of view.
+ */
+#[derive(Debug, Clone)]
+struct MoveStats {
+    command: Command,
     wins: f32,
     losses: f32,
     attempts: f32,
     average: f32,
-    confidence: f32,
-    explored: Vec<(Command, NodeStats)>,
-    unexplored: Vec<Command>,
+    confidence: f32
 }
 
-impl NodeStats {
-    fn create_node(player: &Player) -> NodeStats {
-        let unoccupied_cells_count = player.unoccupied_cell_count();
-        let unoccupied_cells = (0..unoccupied_cells_count)
-            .map(|i| player.location_of_unoccupied_cell(i));
+impl MoveStats {
+    fn new(command: Command) -> MoveStats {
+        MoveStats {
+            command,
+            wins: 0.,
+            losses: 0.,
+            attempts: 0.,
+            average: 0.,
+            confidence: 0.
+        }
+    }
 
-        let mut all_buildings: ArrayVec<[BuildingType; NUMBER_OF_BUILDING_TYPES]> = ArrayVec::new();
-        if DEFENCE_PRICE <= player.energy {
-            all_buildings.push(BuildingType::Defence);
+    fn add_result(&mut self, won: bool, lost: bool) {
+        self.attempts += 1.;
+        if won {
+            self.wins += 1.;
         }
-        if MISSILE_PRICE <= player.energy {
-            all_buildings.push(BuildingType::Attack);
+        if lost {
+            self.losses += 1.;
         }
-        if ENERGY_PRICE <= player.energy {
-            all_buildings.push(BuildingType::Energy);
+        self.update_confidence();
+    }
+
+    fn update_confidence(&mut self) {
+        self.average = self.wins / self.attempts;
+        self.confidence = (2.0 / self.attempts).sqrt();
+    }
+
+    fn ucb(&self, sqrt_n: f32) -> f32 {
+        self.average + sqrt_n * self.confidence
+    }
+}
+
+/**
+ * A state in the game tree. Both players choose their moves here
+ * without seeing each other's, the same as in the real game, so each
+ * player's moves are scored separately (decoupled UCT), and the
+ * children are found by the pair of moves that were played together.
+ *
+ * Different orders of moves often lead to the same state, so a node
+ * can be the child of more than one parent, and its scores are shared
+ * between all the ways of getting there.
+ */
+#[derive(Debug, Default)]
+struct Node {
+    /// The Zobrist hash of the node's state, which finds it in the
+    /// transposition table.
+    hash: u64,
+    /// The node's state, sorted and written as JSON, to check for hash
+    /// collisions.
+    #[cfg(feature = "debug-hashes")]
+    position: String,
+    attempts: f32,
+    /// The moves are only generated the first time the search passes
+    /// through the node. Most nodes are leaves that never need them.
+    expanded: bool,
+    player_moves: Vec<MoveStats>,
+    opponent_moves: Vec<MoveStats>,
+    children: Vec<(Command, Command, NodeId)>
+}
+
+impl Node {
+    fn expand<M: MapSize>(&mut self, state: &BitwiseGameState<M>) {
+        generate_moves(&state.player, &state.config, &mut self.player_moves);
+        generate_moves(&state.opponent, &state.config, &mut self.opponent_moves);
+        self.expanded = true;
+    }
+
+    /// Clears the node for reuse, keeping the space for its moves.
+    fn reset(&mut self) {
+        self.hash = 0;
+        #[cfg(feature = "debug-hashes")]
+        self.position.clear();
+        self.attempts = 0.;
+        self.expanded = false;
+        self.player_moves.clear();
+        self.opponent_moves.clear();
+        self.children.clear();
+    }
+
+    /**
+     * The player's move with the highest UCB. If time ran out before
+     * any moves were tried, it does nothing.
+     */
+    fn best_command(&self) -> Command {
+        let sqrt_n = self.attempts.sqrt();
+        self.player_moves.iter()
+            .filter(|m| m.attempts > 0.)
+            .max_by(|a, b| a.ucb(sqrt_n).partial_cmp(&b.ucb(sqrt_n)).unwrap())
+            .map(|m| m.command)
+            .unwrap_or(Command::Nothing)
+    }
+
+    /**
+     * The number of games played through the player's least tried move
+     * from here. If the player has no moves there's nothing left to
+     * try, so that counts as every move having been tried enough.
+     */
+    fn fewest_player_move_attempts(&self) -> u32 {
+        self.player_moves.iter()
+            .map(|m| m.attempts as u32)
+            .min()
+            .unwrap_or(u32::MAX)
+    }
+
+    /**
+     * Adds up the scores of the player's moves from the roots of
+     * several trees. Only the root's own moves are merged, since
+     * they're all that's needed to choose a move.
+     */
+    fn merge_roots(trees: &[NodePool]) -> Node {
+        let mut merged = Node {
+            player_moves: trees[0].root().player_moves.iter().map(|m| MoveStats::new(m.command)).collect(),
+            ..Node::default()
+        };
+        for root in trees.iter().map(|tree| tree.root()) {
+            merged.attempts += root.attempts;
+            for other_move in &root.player_moves {
+                if let Some(stats) = merged.player_moves.iter_mut().find(|m| m.command == other_move.command) {
+                    stats.wins += other_move.wins;
+                    stats.losses += other_move.losses;
+                    stats.attempts += other_move.attempts;
+                }
+            }
         }
-        if TESLA_PRICE <= player.energy && !player.has_max_teslas() {
-            all_buildings.push(BuildingType::Tesla);
+        for stats in merged.player_moves.iter_mut().filter(|m| m.attempts > 0.) {
+            stats.update_confidence();
         }
-        
-        let building_command_count = unoccupied_cells.len()*all_buildings.len();
+        merged
+    }
+}
 
-        let mut commands = Vec::with_capacity(building_command_count + 2);
+/**
+ * The nodes of one search tree, with the root first, up to a fixed
+ * number of them. When it fills up, the least visited subtrees are
+ * pruned. Their nodes are kept to be reused, along with the space for
+ * their moves, so a long search doesn't keep going back to the
+ * allocator.
+ *
+ * The pool also keeps the transposition table, which finds the node
+ * for a state from its hash.
+ */
+#[derive(Debug)]
+struct NodePool {
+    nodes: Vec<Node>,
+    free: Vec<NodeId>,
+    transpositions: HashMap<u64, NodeId>,
+    capacity: usize
+}
 
-        commands.push(Command::Nothing);
-        if IRON_CURTAIN_PRICE <= player.energy && player.can_build_iron_curtain() {
-            commands.push(Command::IronCurtain);
+impl NodePool {
+    /// The first node allocated from an empty pool is the root.
+    fn empty(capacity: usize) -> NodePool {
+        NodePool {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            transpositions: HashMap::new(),
+            capacity
         }
+    }
+
+    fn with_root(capacity: usize, hash: u64) -> NodePool {
+        let mut pool = NodePool::empty(capacity.max(1));
+        pool.allocate(hash);
+        pool
+    }
+
+    fn root(&self) -> &Node {
+        &self.nodes[ROOT as usize]
+    }
+
+    fn node(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id as usize]
+    }
 
-        for position in unoccupied_cells {
-            for &building in &all_buildings {
-                commands.push(Command::Build(position, building));
+    fn in_use(&self) -> usize {
+        self.nodes.len() - self.free.len()
+    }
+
+    fn is_full(&self) -> bool {
+        self.free.is_empty() && self.nodes.len() >= self.capacity
+    }
+
+    /**
+     * A new node for the state with the given hash, which is added to
+     * the transposition table. There's no node if the pool is full.
+     */
+    fn allocate(&mut self, hash: u64) -> Option<NodeId> {
+        let id = if let Some(id) = self.free.pop() {
+            self.node(id).reset();
+            id
+        } else if self.nodes.len() < self.capacity {
+            self.nodes.push(Node::default());
+            (self.nodes.len() - 1) as NodeId
+        } else {
+            return None;
+        };
+        self.node(id).hash = hash;
+        self.transpositions.insert(hash, id);
+        Some(id)
+    }
+
+    fn find(&self, hash: u64) -> Option<NodeId> {
+        self.transpositions.get(&hash).cloned()
+    }
+
+    /**
+     * Frees the least visited subtrees until a quarter of the pool is
+     * free. The scores of the moves that led to them are kept in their
+     * parents, so only the detail further down is lost. A node that
+     * can still be reached from another parent is kept.
+     */
+    fn prune(&mut self) {
+        let target = (self.capacity / 4).max(1);
+
+        let mut edges = Vec::with_capacity(self.in_use());
+        let mut seen = vec!(false; self.nodes.len());
+        let mut stack = vec!(ROOT);
+        while let Some(parent) = stack.pop() {
+            for &(_, _, child) in &self.nodes[parent as usize].children {
+                edges.push((self.nodes[child as usize].attempts, parent, child));
+                if !seen[child as usize] {
+                    seen[child as usize] = true;
+                    stack.push(child);
+                }
             }
         }
-        
-        NodeStats {
-            wins: 0.,
-            losses: 0.,
-            attempts: 0.,
-            average: 0.,
-            confidence: 0.,
-            explored: Vec::with_capacity(commands.len()),
-            unexplored: commands
+        edges.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        let mut edges = edges.into_iter();
+        while self.free.len() < target {
+            let cut: Vec<_> = edges.by_ref().take(target - self.free.len()).collect();
+            if cut.is_empty() {
+                break;
+            }
+            for (_, parent, child) in cut {
+                self.node(parent).children.retain(|&(_, _, c)| c != child);
+            }
+            self.free_unreachable();
         }
     }
-    
-    fn node_with_highest_ucb<'a>(&'a mut self) -> &'a mut (Command, NodeStats) {
-        debug_assert!(self.unexplored.is_empty());
-        debug_assert!(self.explored.len() > 0);
-        let sqrt_n = self.attempts.sqrt();
 
-        let mut max_position = 0;
-        let mut max_value = self.explored[0].1.ucb(sqrt_n);
-        for i in 1..self.explored.len() {
-            let value = self.explored[i].1.ucb(sqrt_n);
-            if value > max_value {
-                max_position = i;
-                max_value = value;
+    fn free_unreachable(&mut self) {
+        let mut keep = vec!(false; self.nodes.len());
+        keep[ROOT as usize] = true;
+        let mut stack = vec!(ROOT);
+        while let Some(id) = stack.pop() {
+            for &(_, _, child) in &self.nodes[id as usize].children {
+                if !keep[child as usize] {
+                    keep[child as usize] = true;
+                    stack.push(child);
+                }
             }
         }
-        &mut self.explored[max_position]
-    }
+        for &id in &self.free {
+            keep[id as usize] = true;
+        }
 
-    fn ucb(&self, sqrt_n: f32) -> f32 {
-        self.average + sqrt_n * self.confidence
+        for (id, _) in keep.iter().enumerate().filter(|&(_, &keep)| !keep) {
+            let hash = self.nodes[id].hash;
+            if self.find(hash) == Some(id as NodeId) {
+                self.transpositions.remove(&hash);
+            }
+            self.free.push(id as NodeId);
+        }
     }
+}
 
-    fn add_node<'a>(&'a mut self, player: &Player, command: Command) -> &'a mut (Command, NodeStats) {
-        let node = NodeStats::create_node(player);
-        self.explored.push((command, node));
-        self.unexplored.retain(|c| *c != command);
-        self.explored.last_mut().unwrap()
+/**
+ * Picks a move that hasn't been tried yet at random, or the move with
+ * the highest UCB once they all have been.
+ */
+fn select_move<R: Rng>(moves: &[MoveStats], attempts: f32, rng: &mut R) -> usize {
+    debug_assert!(!moves.is_empty());
+    let untried = moves.iter().filter(|m| m.attempts == 0.).count();
+    if untried > 0 {
+        let choice = rng.gen_range(0, untried);
+        return moves.iter().enumerate()
+            .filter(|(_, m)| m.attempts == 0.)
+            .nth(choice)
+            .map(|(i, _)| i)
+            .expect("Chose an untried move that doesn't exist");
     }
 
-    fn add_victory(&mut self) {
-        self.attempts += 1.;
-        self.wins += 1.;
-        self.update_confidence();
-    }
-    fn add_defeat(&mut self) {
-        self.attempts += 1.;
-        self.losses += 1.;
-        self.update_confidence();
-    }
-    fn add_draw(&mut self) {
-        self.attempts += 1.;
-        self.update_confidence();
+    let sqrt_n = attempts.sqrt();
+    let mut max_position = 0;
+    let mut max_value = moves[0].ucb(sqrt_n);
+    for (i, m) in moves.iter().enumerate().skip(1) {
+        let value = m.ucb(sqrt_n);
+        if value > max_value {
+            max_position = i;
+            max_value = value;
+        }
     }
-    fn update_confidence(&mut self) {
-        self.average = self.wins / self.attempts;
-        self.confidence = (2.0 / self.attempts).sqrt();
+    max_position
+}
+
+fn generate_moves<M: MapSize>(player: &Player<M>, config: &GameConfig, moves: &mut Vec<MoveStats>) {
+    moves.clear();
+    moves.extend(player.sensible_commands(config, false).iter().map(MoveStats::new));
+}
+
+/**
+ * Builds a game tree where both players move at the same time, using
+ * UCB to decide which moves each player explores. If `budget` is set,
+ * it's used instead of the time limit for the turn.
+ *
+ * Several trees are searched in parallel, each with its own seed,
+ * and the scores of their first moves are added together. `trees`
+ * sets how many. Otherwise there's one for each thread when searching
+ * for a time, and `DEFAULT_TREE_COUNT` for any other budget.
+ *
+ * If `tree_file` is set, the part of the trees after the chosen move
+ * is saved there at the end of the turn. The next turn carries on
+ * searching from the part that matches the moves that were actually
+ * played.
+ *
+ * The trees keep at most `max_nodes` nodes between them, or
+ * `DEFAULT_MAX_NODES`, pruning the least visited parts when they're
+ * full.
+ */
+#[derive(Debug, Clone)]
+pub struct MonteCarloTree {
+    pub random_moves: RandomMoveOptions,
+    pub seed: [u8; 16],
+    pub budget: Option<SearchBudget>,
+    pub trees: Option<usize>,
+    pub max_nodes: Option<usize>,
+    pub tree_file: Option<PathBuf>
+}
+
+impl Default for MonteCarloTree {
+    fn default() -> MonteCarloTree {
+        MonteCarloTree {
+            random_moves: RandomMoveOptions::default(),
+            seed: INIT_SEED,
+            budget: None,
+            trees: None,
+            max_nodes: None,
+            tree_file: None
+        }
     }
+}
 
-    #[cfg(feature = "benchmarking")]
-    fn count_explored(&self) -> usize {
-        1 + self.explored.iter().map(|(_, n)| n.count_explored()).sum::<usize>()
+impl<M: MapSize> Strategy<M> for MonteCarloTree {
+    fn choose_move(&mut self, state: &BitwiseGameState<M>, start_time: PreciseTime, max_time: Duration) -> Command {
+        let budget = SearchBudget::or_time(self.budget, max_time);
+        let trees = self.trees.unwrap_or_else(|| default_tree_count(budget)).max(1);
+        let nodes_per_tree = nodes_per_tree(self.max_nodes.unwrap_or(DEFAULT_MAX_NODES), trees);
+        let saved_trees = match self.tree_file {
+            Some(ref path) => load_tree_file(path, state, nodes_per_tree).unwrap_or_else(|error| {
+                println!("Error while reading the saved search tree, starting afresh: {}", error);
+                Vec::new()
+            }),
+            None => Vec::new()
+        };
+
+        let (command, searched_trees) = search(state, start_time, budget, trees, nodes_per_tree, saved_trees, self.seed, &self.random_moves);
+        if let Some(ref path) = self.tree_file {
+            if let Err(error) = save_tree_file(path, state, command, &searched_trees) {
+                println!("Error while saving the search tree: {}", error);
+            }
+        }
+        command
     }
 }
 
-pub fn choose_move(state: &BitwiseGameState, start_time: PreciseTime, max_time: Duration) -> Command {
-    let mut rng = XorShiftRng::from_seed(INIT_SEED);
-    
-    let mut root = NodeStats::create_node(&state.player);
+pub fn choose_move<M: MapSize>(state: &BitwiseGameState<M>, start_time: PreciseTime, max_time: Duration, random_moves: &RandomMoveOptions) -> Command {
+    let budget = SearchBudget::or_time(None, max_time);
+    let trees = default_tree_count(budget);
+    search(state, start_time, budget, trees, nodes_per_tree(DEFAULT_MAX_NODES, trees), Vec::new(), INIT_SEED, random_moves).0
+}
+
+/**
+ * Splits the nodes between the trees. Each tree has room for at least
+ * one node besides its root, so that it can always grow after pruning.
+ */
+fn nodes_per_tree(max_nodes: usize, trees: usize) -> usize {
+    (max_nodes / trees.max(1)).max(2)
+}
+
+#[cfg(not(feature = "single-threaded"))]
+fn default_tree_count(budget: SearchBudget) -> usize {
+    if budget.is_time() { rayon::current_num_threads() } else { DEFAULT_TREE_COUNT }
+}
+
+#[cfg(feature = "single-threaded")]
+fn default_tree_count(budget: SearchBudget) -> usize {
+    if budget.is_time() { 1 } else { DEFAULT_TREE_COUNT }
+}
 
-    while start_time.to(PreciseTime::now()) < max_time {
-        tree_search(&state, &mut root, &mut rng);
+/**
+ * Searches the trees, starting from `saved_trees` where there are any,
+ * and returns the chosen move along with the trees.
+ */
+#[allow(clippy::too_many_arguments)]
+fn search<M: MapSize>(state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, trees: usize, nodes_per_tree: usize, saved_trees: Vec<NodePool>, seed: [u8; 16], random_moves: &RandomMoveOptions) -> (Command, Vec<NodePool>) {
+    let trees = trees.max(1);
+    let tree_budget = budget.share(trees as u32);
+    let mut starting_trees: Vec<NodePool> = saved_trees.into_iter().take(trees).collect();
+    while starting_trees.len() < trees {
+        starting_trees.push(NodePool::with_root(nodes_per_tree, state.zobrist_hash()));
     }
+    let searched_trees = search_trees(state, start_time, tree_budget, starting_trees, seed, random_moves);
 
     #[cfg(feature = "benchmarking")]
     {
-        println!("Explored nodes: {}", root.count_explored());
+        println!("Explored nodes: {}", searched_trees.iter().map(|tree| tree.in_use()).sum::<usize>());
     }
 
-    let (command, _) = root.node_with_highest_ucb();
-    command.clone()
+    (Node::merge_roots(&searched_trees).best_command(), searched_trees)
 }
 
-fn tree_search<R: Rng>(state: &BitwiseGameState, stats: &mut NodeStats, rng: &mut R) -> GameStatus {
-    // root is opponent move
-    // node being added is player move
-    
-    if state.round >= MAX_MOVES {
-        return GameStatus::Draw
+#[cfg(feature = "single-threaded")]
+fn search_trees<M: MapSize>(state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, trees: Vec<NodePool>, seed: [u8; 16], random_moves: &RandomMoveOptions) -> Vec<NodePool> {
+    trees.into_iter().enumerate()
+        .map(|(i, tree)| search_tree(state, start_time, budget, tree, tree_seed(seed, i), random_moves))
+        .collect()
+}
+
+#[cfg(not(feature = "single-threaded"))]
+fn search_trees<M: MapSize>(state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, trees: Vec<NodePool>, seed: [u8; 16], random_moves: &RandomMoveOptions) -> Vec<NodePool> {
+    trees.into_par_iter().enumerate()
+        .map(|(i, tree)| search_tree(state, start_time, budget, tree, tree_seed(seed, i), random_moves))
+        .collect()
+}
+
+/**
+ * The first tree uses the strategy's seed, so that searching a single
+ * tree is the same as before there were several.
+ */
+fn tree_seed(seed: [u8; 16], tree: usize) -> [u8; 16] {
+    let mut result = seed;
+    let mixed = (tree as u64).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    for (i, byte) in mixed.to_le_bytes().iter().enumerate() {
+        result[i] ^= byte;
     }
-    
-    if stats.unexplored.is_empty() {
-        let result = {
-            let (next_command, next_tree) = stats.node_with_highest_ucb();
-            tree_search_opponent(state, next_tree, next_command.clone(), rng)
-        };
-        match result {
-            GameStatus::PlayerWon => {stats.add_defeat()},
-            GameStatus::OpponentWon => {stats.add_victory()},
-            _ => {stats.add_draw()}
-        };
-        result
-    } else {
-        let next_command = rng.choose(&stats.unexplored).expect("Partially explored had no options").clone();
-        let result = {
-            let (_, next_stats) = stats.add_node(&state.opponent, next_command);
-
-            let opponent_random = monte_carlo::random_move(&state.opponent, &state.player, rng);
-            let mut next_state = state.clone();
-            next_state.simulate(next_command, opponent_random);
-
-            let result = simulate_to_endstate(next_state, rng);
-            match result {
-                GameStatus::PlayerWon => {next_stats.add_victory()},
-                GameStatus::OpponentWon => {next_stats.add_defeat()},
-                _ => {next_stats.add_draw()}
-            };
-            
-            result
-        };
+    result
+}
 
-        match result {
-            GameStatus::PlayerWon => {stats.add_defeat()},
-            GameStatus::OpponentWon => {stats.add_victory()},
-            _ => {stats.add_draw()}
-        };
-        result
+fn search_tree<M: MapSize>(state: &BitwiseGameState<M>, start_time: PreciseTime, budget: SearchBudget, mut tree: NodePool, seed: [u8; 16], random_moves: &RandomMoveOptions) -> NodePool {
+    let mut rng = XorShiftRng::from_seed(seed);
+    let mut state = state.clone();
+    state.rehash();
+
+    // There are no moves to search once the game is over
+    let finished = state.status != GameStatus::Continue || state.round >= state.config.max_rounds;
+    let mut progress = SearchProgress::default();
+    while !finished && !budget.spent(start_time, &progress) {
+        // Each search adds at most one node, so there's always room
+        // for it after pruning.
+        if tree.is_full() {
+            tree.prune();
+        }
+        let nodes = progress.nodes;
+        tree_search(&mut state, &mut tree, ROOT, random_moves, &mut rng, &mut progress);
+        // A search that ends at the end of the game, or that finds the
+        // tree full, adds no node. It still counts, or a node budget
+        // might never be spent.
+        if progress.nodes == nodes {
+            progress.nodes += 1;
+        }
+        progress.rollouts += 1;
+        progress.rollouts_per_candidate = tree.root().fewest_player_move_attempts();
     }
+    tree
 }
 
-fn tree_search_opponent<R: Rng>(state: &BitwiseGameState, stats: &mut NodeStats, player_command: Command, rng: &mut R) -> GameStatus {
-    // root is player move
-    // node being added is opponent move
+/**
+ * Chooses a move for each player, then carries on down the tree from
+ * the state after both moves. If that state isn't in the tree yet,
+ * it's added and the rest of the game is played out randomly. The
+ * moves are undone on the way back up, so `state` is left as it was.
+ *
+ * The state's hash is kept up to date along the way, to find states
+ * that were reached by other moves in the transposition table.
+ */
+fn tree_search<M: MapSize, R: Rng>(state: &mut BitwiseGameState<M>, tree: &mut NodePool, id: NodeId, random_moves: &RandomMoveOptions, rng: &mut R, progress: &mut SearchProgress) -> GameStatus {
+    #[cfg(feature = "debug-hashes")]
+    check_for_hash_collision(tree.node(id), state);
 
-    if stats.unexplored.is_empty() {
-        let result = {
-            let (next_command, next_tree) = stats.node_with_highest_ucb();
-            let mut next_state = state.clone();
-            next_state.simulate(player_command, next_command.clone());
-            tree_search(&next_state, next_tree, rng)
-        };
-        match result {
-            GameStatus::PlayerWon => {stats.add_victory()},
-            GameStatus::OpponentWon => {stats.add_defeat()},
-            _ => {stats.add_draw()}
-        };
-        result
-    } else {
-        let next_command = rng.choose(&stats.unexplored).expect("Partially explored had no options").clone();
-        let mut next_state = state.clone();
-        next_state.simulate(player_command, next_command);
-
-        let result = {
-            let (_, next_stats) = stats.add_node(&next_state.player, next_command);
-
-            let result = simulate_to_endstate(next_state, rng);
-            match result {
-                GameStatus::PlayerWon => {next_stats.add_defeat()},
-                GameStatus::OpponentWon => {next_stats.add_victory()},
-                _ => {next_stats.add_draw()}
-            };
-            
-            result
-        };
-        
-        match result {
-            GameStatus::PlayerWon => {stats.add_victory()},
-            GameStatus::OpponentWon => {stats.add_defeat()},
-            _ => {stats.add_draw()}
-        };
-        result
+    if state.status != GameStatus::Continue {
+        return state.status;
+    }
+    if state.round >= state.config.max_rounds {
+        return GameStatus::Draw
     }
+
+    let (player_index, opponent_index, player_command, opponent_command, existing_child) = {
+        let node = tree.node(id);
+        if !node.expanded {
+            node.expand(state);
+        }
+        let player_index = select_move(&node.player_moves, node.attempts, rng);
+        let opponent_index = select_move(&node.opponent_moves, node.attempts, rng);
+        let player_command = node.player_moves[player_index].command;
+        let opponent_command = node.opponent_moves[opponent_index].command;
+        let existing_child = node.children.iter()
+            .find(|&&(p, o, _)| p == player_command && o == opponent_command)
+            .map(|&(_, _, child)| child);
+        (player_index, opponent_index, player_command, opponent_command, existing_child)
+    };
+
+    let (_, undo) = state.simulate_with_undo(player_command, opponent_command);
+    let next_hash = state.hash();
+
+    let transposition = match existing_child {
+        Some(_) => None,
+        None => tree.find(next_hash)
+    };
+    if let Some(child) = transposition {
+        tree.node(id).children.push((player_command, opponent_command, child));
+    }
+
+    let result = match existing_child.or(transposition) {
+        Some(child) => tree_search(state, tree, child, random_moves, rng, progress),
+        None => {
+            if let Some(child) = tree.allocate(next_hash) {
+                tree.node(id).children.push((player_command, opponent_command, child));
+                progress.nodes += 1;
+            }
+            // The rest of the game is random, so like the rollouts in
+            // `monte_carlo` it's played on a copy instead of undone
+            simulate_to_endstate(state.clone(), random_moves, rng)
+        }
+    };
+    state.unsimulate(undo);
+
+    let node = tree.node(id);
+    node.attempts += 1.;
+    node.player_moves[player_index].add_result(result == GameStatus::PlayerWon, result == GameStatus::OpponentWon);
+    node.opponent_moves[opponent_index].add_result(result == GameStatus::OpponentWon, result == GameStatus::PlayerWon);
+    result
 }
 
+/**
+ * Checks that the hash was updated correctly, and that the node's
+ * hash wasn't already used by a different state.
+ */
+#[cfg(feature = "debug-hashes")]
+fn check_for_hash_collision<M: MapSize>(node: &mut Node, state: &BitwiseGameState<M>) {
+    let hash = state.hash();
+    assert_eq!(hash, state.zobrist_hash(), "The hash wasn't updated correctly");
+    let mut sorted = state.sorted();
+    // The order the teslas fire in matters, so they're compared as
+    // they are. Their ages don't, and the hash leaves them out.
+    sorted.player.tesla_cooldowns = state.player.tesla_cooldowns.clone();
+    sorted.opponent.tesla_cooldowns = state.opponent.tesla_cooldowns.clone();
+    for tesla in sorted.player.tesla_cooldowns.iter_mut().chain(sorted.opponent.tesla_cooldowns.iter_mut()) {
+        tesla.age = 0;
+    }
+    let position = input::json::state_to_string(&sorted).unwrap();
+    if node.position.is_empty() {
+        node.position = position;
+    } else {
+        assert!(node.position == position, "Hash collision on {:016x}:\n{}\n{}", hash, node.position, position);
+    }
+}
 
-fn simulate_to_endstate<R: Rng>(mut state: BitwiseGameState, rng: &mut R) -> GameStatus  {
-    let mut status = GameStatus::Continue;
+fn simulate_to_endstate<M: MapSize, R: Rng>(mut state: BitwiseGameState<M>, random_moves: &RandomMoveOptions, rng: &mut R) -> GameStatus  {
+    let mut status = state.status;
     
-    while status == GameStatus::Continue && state.round < MAX_MOVES {
-        let player_command = monte_carlo::random_move(&state.player, &state.opponent, rng);
-        let opponent_command = monte_carlo::random_move(&state.opponent, &state.player, rng);
+    while status == GameStatus::Continue && state.round < state.config.max_rounds {
+        let player_command = monte_carlo::random_move(&state.player, &state.opponent, &state.config, random_moves, rng);
+        let opponent_command = monte_carlo::random_move(&state.opponent, &state.player, &state.config, random_moves, rng);
         status = state.simulate(player_command, opponent_command);
     }
     status
 }
 
+/**
+ * Saves the part of each tree that follows the chosen move, since
+ * that's the only part the next turn could use. The state is saved
+ * with it, so that the next turn can tell which of the opponent's
+ * moves was played. A node that's shared by several parents is only
+ * saved in full once in each subtree.
+ */
+fn save_tree_file<M: MapSize>(path: &Path, state: &BitwiseGameState<M>, command: Command, trees: &[NodePool]) -> Result<(), Box<dyn Error>> {
+    if let Some(folder) = path.parent() {
+        fs::create_dir_all(folder)?;
+    }
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(TREE_FILE_MAGIC)?;
+    let state_json = input::json::state_to_string(state)?;
+    write_u32(&mut writer, state_json.len() as u32)?;
+    writer.write_all(state_json.as_bytes())?;
+    write_command(&mut writer, command)?;
+
+    write_u32(&mut writer, trees.len() as u32)?;
+    for tree in trees {
+        let subtrees: Vec<(Command, NodeId)> = tree.root().children.iter()
+            .filter(|&&(p, _, _)| p == command)
+            .map(|&(_, o, child)| (o, child))
+            .collect();
+        write_u32(&mut writer, subtrees.len() as u32)?;
+        for (opponent_command, child) in subtrees {
+            write_command(&mut writer, opponent_command)?;
+            write_node(&mut writer, tree, child, &mut vec!(false; tree.nodes.len()))?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/**
+ * The number of games that the trees saved in `path` have already
+ * played from `state`, which is zero if they can't be reused there.
+ */
+pub fn saved_tree_rollouts<M: MapSize>(path: &Path, state: &BitwiseGameState<M>) -> Result<u32, Box<dyn Error>> {
+    Ok(load_tree_file(path, state, DEFAULT_MAX_NODES)?.iter().map(|tree| tree.root().attempts as u32).sum())
+}
+
+/**
+ * Finds the saved subtrees that start from `state`, if the file was
+ * saved on the previous round. The opponent's move is the one that
+ * takes the saved state to this one. Each subtree is loaded into a
+ * pool of `capacity` nodes, and whatever doesn't fit is dropped.
+ */
+fn load_tree_file<M: MapSize>(path: &Path, state: &BitwiseGameState<M>, capacity: usize) -> Result<Vec<NodePool>, Box<dyn Error>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+    if &magic != TREE_FILE_MAGIC {
+        return Err("Not a saved search tree".into());
+    }
+    let mut state_json = vec!(0; read_u32(&mut reader)? as usize);
+    reader.read_exact(&mut state_json)?;
+    let previous_state: BitwiseGameState<M> = input::json::state_from_str(&String::from_utf8(state_json)?)?;
+    if previous_state.round + 1 != state.round {
+        return Ok(Vec::new());
+    }
+    let player_command = read_command(&mut reader)?;
+
+    let mut actual_state = state.clone();
+    actual_state.sort();
+    let mut checked_commands: Vec<(Command, bool)> = Vec::new();
+    let mut leads_to_state = |opponent_command: Command| {
+        if let Some(&(_, matches)) = checked_commands.iter().find(|&&(o, _)| o == opponent_command) {
+            return matches;
+        }
+        let mut next_state = previous_state.clone();
+        next_state.simulate(player_command, opponent_command);
+        next_state.sort();
+        let matches = next_state == actual_state;
+        checked_commands.push((opponent_command, matches));
+        matches
+    };
+
+    let mut trees = Vec::new();
+    let mut any_matched = false;
+    for _ in 0..read_u32(&mut reader)? {
+        let mut tree = None;
+        for _ in 0..read_u32(&mut reader)? {
+            let opponent_command = read_command(&mut reader)?;
+            if tree.is_none() && leads_to_state(opponent_command) {
+                let mut pool = NodePool::empty(capacity.max(1));
+                read_node(&mut reader, &mut pool)?;
+                tree = Some(pool);
+            } else {
+                read_node(&mut reader, &mut NodePool::empty(0))?;
+            }
+        }
+        any_matched = any_matched || tree.is_some();
+        trees.push(tree.unwrap_or_else(|| NodePool::with_root(capacity, state.zobrist_hash())));
+    }
+
+    if any_matched {
+        Ok(trees)
+    } else {
+        Ok(Vec::new())
+    }
+}
+
+fn write_node<W: Write>(writer: &mut W, tree: &NodePool, id: NodeId, written: &mut [bool]) -> Result<(), Box<dyn Error>> {
+    let node = &tree.nodes[id as usize];
+    write_u64(writer, node.hash)?;
+    if written[id as usize] {
+        writer.write_all(&[SHARED_NODE])?;
+        return Ok(());
+    }
+    written[id as usize] = true;
+    writer.write_all(&[SAVED_NODE])?;
+
+    write_f32(writer, node.attempts)?;
+    writer.write_all(&[node.expanded as u8])?;
+    for moves in &[&node.player_moves, &node.opponent_moves] {
+        write_u32(writer, moves.len() as u32)?;
+        for stats in moves.iter() {
+            write_command(writer, stats.command)?;
+            write_f32(writer, stats.wins)?;
+            write_f32(writer, stats.losses)?;
+            write_f32(writer, stats.attempts)?;
+        }
+    }
+    write_u32(writer, node.children.len() as u32)?;
+    for &(player_command, opponent_command, child) in &node.children {
+        write_command(writer, player_command)?;
+        write_command(writer, opponent_command)?;
+        write_node(writer, tree, child, written)?;
+    }
+    Ok(())
+}
+
+const SAVED_NODE: u8 = 0;
+const SHARED_NODE: u8 = 1;
+
+fn read_moves<R: Read>(reader: &mut R) -> Result<Vec<MoveStats>, Box<dyn Error>> {
+    let count = read_u32(reader)?;
+    let mut moves = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let mut stats = MoveStats::new(read_command(reader)?);
+        stats.wins = read_f32(reader)?;
+        stats.losses = read_f32(reader)?;
+        stats.attempts = read_f32(reader)?;
+        if stats.attempts > 0. {
+            stats.update_confidence();
+        }
+        moves.push(stats);
+    }
+    Ok(moves)
+}
+
+/**
+ * Reads a node and its children into `tree`, parents first. Nodes that
+ * don't fit in the pool are still read, so the reader moves past them,
+ * but they're dropped along with their children. A node that was
+ * already read for another parent is found by its hash.
+ */
+fn read_node<R: Read>(reader: &mut R, tree: &mut NodePool) -> Result<Option<NodeId>, Box<dyn Error>> {
+    let hash = read_u64(reader)?;
+    let mut saved = [0];
+    reader.read_exact(&mut saved)?;
+    if saved[0] == SHARED_NODE {
+        return Ok(tree.find(hash));
+    }
+
+    let attempts = read_f32(reader)?;
+    let mut expanded = [0];
+    reader.read_exact(&mut expanded)?;
+    let player_moves = read_moves(reader)?;
+    let opponent_moves = read_moves(reader)?;
+
+    let id = tree.allocate(hash);
+    if let Some(id) = id {
+        let node = tree.node(id);
+        node.attempts = attempts;
+        node.expanded = expanded[0] != 0;
+        node.player_moves = player_moves;
+        node.opponent_moves = opponent_moves;
+    }
+
+    for _ in 0..read_u32(reader)? {
+        let player_command = read_command(reader)?;
+        let opponent_command = read_command(reader)?;
+        if let (Some(child), Some(id)) = (read_node(reader, tree)?, id) {
+            tree.node(id).children.push((player_command, opponent_command, child));
+        }
+    }
+
+    Ok(id)
+}
+
+/**
+ * Commands are saved as their action type, as in `command.txt`,
+ * followed by the point.
+ */
+fn write_command<W: Write>(writer: &mut W, command: Command) -> Result<(), Box<dyn Error>> {
+    let bytes = match command {
+        Command::Nothing => [NOTHING_ACTION, 0, 0],
+        Command::Build(p, building) => [building as u8, p.x(), p.y()],
+        Command::Deconstruct(p) => [DECONSTRUCT_ACTION, p.x(), p.y()],
+        Command::IronCurtain => [IRON_CURTAIN_ACTION, 0, 0]
+    };
+    writer.write_all(&bytes)?;
+    Ok(())
+}
+
+fn read_command<R: Read>(reader: &mut R) -> Result<Command, Box<dyn Error>> {
+    let mut bytes = [0; 3];
+    reader.read_exact(&mut bytes)?;
+    let point = Point::new(bytes[1], bytes[2]);
+    match bytes[0] {
+        NOTHING_ACTION => Ok(Command::Nothing),
+        DECONSTRUCT_ACTION => Ok(Command::Deconstruct(point)),
+        IRON_CURTAIN_ACTION => Ok(Command::IronCurtain),
+        action => BuildingType::from_u8(action)
+            .map(|building| Command::Build(point, building))
+            .ok_or_else(|| format!("Unknown action type {}", action).into())
+    }
+}
+
+const NOTHING_ACTION: u8 = 255;
+const DECONSTRUCT_ACTION: u8 = 3;
+const IRON_CURTAIN_ACTION: u8 = 5;
+
+fn write_u32<W: Write>(writer: &mut W, value: u32) -> Result<(), Box<dyn Error>> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> Result<u32, Box<dyn Error>> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn write_u64<W: Write>(writer: &mut W, value: u64) -> Result<(), Box<dyn Error>> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> Result<u64, Box<dyn Error>> {
+    let mut bytes = [0; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+fn write_f32<W: Write>(writer: &mut W, value: f32) -> Result<(), Box<dyn Error>> {
+    writer.write_all(&value.to_le_bytes())?;
+    Ok(())
+}
+
+fn read_f32<R: Read>(reader: &mut R) -> Result<f32, Box<dyn Error>> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
@@ -0,0 +1,32 @@
+use engine::command::Command;
+use engine::bitwise_engine::BitwiseGameState;
+use engine::bitboard::MapSize;
+use strategy::Strategy;
+use strategy::monte_carlo::{self, RandomMoveOptions};
+
+use rand::{XorShiftRng, SeedableRng};
+use time::{Duration, PreciseTime};
+
+/**
+ * Plays the same random moves that are used in the Monte Carlo
+ * simulations. Mostly useful as an opponent for the other strategies.
+ */
+pub struct Random {
+    pub random_moves: RandomMoveOptions,
+    rng: XorShiftRng
+}
+
+impl Random {
+    pub fn new(random_moves: RandomMoveOptions, seed: [u8; 16]) -> Random {
+        Random {
+            random_moves,
+            rng: XorShiftRng::from_seed(seed)
+        }
+    }
+}
+
+impl<M: MapSize> Strategy<M> for Random {
+    fn choose_move(&mut self, state: &BitwiseGameState<M>, _start_time: PreciseTime, _max_time: Duration) -> Command {
+        monte_carlo::random_move(&state.player, &state.opponent, &state.config, &self.random_moves, &mut self.rng)
+    }
+}
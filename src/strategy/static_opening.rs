@@ -1,10 +1,32 @@
 use engine::geometry::*;
 use engine::command::*;
 use engine::bitwise_engine::*;
+use engine::bitboard::MapSize;
+use strategy::Strategy;
+
+use time::{Duration, PreciseTime};
 
 pub const STATIC_OPENING_LENGTH: u16 = 12;
 
-pub fn choose_move(state: &BitwiseGameState) -> Command {
+/**
+ * Plays a fixed opening of energy towers, then hands over to another
+ * strategy.
+ */
+pub struct StaticOpening<M: MapSize> {
+    pub fallback: Box<dyn Strategy<M>>
+}
+
+impl<M: MapSize> Strategy<M> for StaticOpening<M> {
+    fn choose_move(&mut self, state: &BitwiseGameState<M>, start_time: PreciseTime, max_time: Duration) -> Command {
+        if state.round < STATIC_OPENING_LENGTH {
+            choose_move(state)
+        } else {
+            self.fallback.choose_move(state, start_time, max_time)
+        }
+    }
+}
+
+pub fn choose_move<M: MapSize>(state: &BitwiseGameState<M>) -> Command {
     match state.round {
         0 => Command::Build(Point::new(0,0), BuildingType::Energy),
         3 => Command::Build(Point::new(0,1), BuildingType::Energy),
@@ -0,0 +1,58 @@
+extern crate zombot;
+extern crate time;
+
+use time::Duration;
+
+use zombot::arena::bot::Bot;
+use zombot::arena::referee;
+use zombot::arena::tournament::TournamentResults;
+use zombot::engine::status::GameStatus;
+use zombot::engine::config::GameConfig;
+use zombot::engine::constants::*;
+use zombot::engine::bitboard::Map16x8;
+
+#[test]
+fn it_plays_a_whole_game_between_random_bots() {
+    let state = referee::initial_state::<Map16x8>(GameConfig::default());
+    let random: Bot = "random".parse().unwrap();
+    let result = referee::play_match(state, &random, &random, Duration::milliseconds(0), INIT_SEED, None, referee::ShowBoard::Never).unwrap();
+
+    assert!(result.rounds <= MAX_MOVES);
+    match result.status {
+        GameStatus::PlayerWon => assert_eq!(result.opponent_health, 0),
+        GameStatus::OpponentWon => assert_eq!(result.player_health, 0),
+        GameStatus::Draw => assert_eq!((result.player_health, result.opponent_health), (0, 0)),
+        GameStatus::Continue => assert_eq!(result.rounds, MAX_MOVES),
+    }
+}
+
+fn results_with(a_wins: u32, b_wins: u32) -> TournamentResults {
+    let mut results = TournamentResults::new(vec!("a".to_string(), "b".to_string()));
+    for _ in 0..a_wins {
+        results.record(0, 1, GameStatus::PlayerWon);
+    }
+    for _ in 0..b_wins {
+        results.record(1, 0, GameStatus::PlayerWon);
+    }
+    results
+}
+
+#[test]
+fn it_rates_evenly_matched_bots_the_same() {
+    let ratings = results_with(10, 10).ratings();
+
+    assert!(ratings[0].elo.abs() < 1.0);
+    assert!(ratings[1].elo.abs() < 1.0);
+}
+
+#[test]
+fn it_rates_the_stronger_bot_higher_with_more_confidence_from_more_games() {
+    let few = results_with(6, 2).ratings();
+    let many = results_with(60, 20).ratings();
+
+    assert!(few[0].elo > few[1].elo);
+    assert!(many[0].elo > many[1].elo);
+    assert!(many[0].error < few[0].error);
+    // 75% expected score is about 190 Elo apart
+    assert!((many[0].elo - many[1].elo - 190.0).abs() < 10.0);
+}
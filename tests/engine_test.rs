@@ -0,0 +1,414 @@
+extern crate zombot;
+extern crate rand;
+
+use zombot::input::json;
+use zombot::engine::command::{Command, CommandError, BuildingType};
+use zombot::engine::geometry::Point;
+use zombot::engine::bitboard::{Map16x8, Map20x10};
+use zombot::engine::bitwise_engine::{BitwiseGameState, Player};
+use zombot::engine::config::GameConfig;
+use zombot::engine::status::GameStatus;
+use zombot::engine::bitboard::MapSize;
+use zombot::engine::constants::INIT_SEED;
+use zombot::arena::tournament;
+use zombot::strategy::monte_carlo::{self, RandomMoveOptions};
+
+use rand::{SeedableRng, XorShiftRng};
+
+use std::env;
+use std::fs;
+use std::process;
+
+const STATE_PATH: &str = "tests/state0.json";
+
+#[test]
+fn it_deconstructs_a_building_and_refunds_energy() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let point = Point::new(0, 0);
+
+    state.simulate(Command::Build(point, BuildingType::Energy), Command::Nothing);
+    state.simulate(Command::Nothing, Command::Nothing);
+    assert_eq!(state.player.energy_towers, point.to_either_bitfield::<Map16x8>());
+
+    let energy_before = state.player.energy;
+    let generated = state.config.energy_generated_base;
+    state.simulate(Command::Deconstruct(point), Command::Nothing);
+
+    assert_eq!(state.player.occupied, 0);
+    assert_eq!(state.player.energy_towers, 0);
+    assert_eq!(state.player.energy, energy_before + state.config.deconstruct_energy + generated);
+}
+
+#[test]
+fn it_deconstructs_a_building_under_construction() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.player.energy = state.config.tesla_price;
+    let point = Point::new(0, 7);
+
+    state.simulate(Command::Build(point, BuildingType::Tesla), Command::Nothing);
+    assert_eq!(state.player.count_teslas(), 1);
+
+    state.simulate(Command::Deconstruct(point), Command::Nothing);
+    assert_eq!(state.player.count_teslas(), 0);
+    assert_eq!(state.player.occupied, 0);
+}
+
+#[test]
+fn it_undoes_rounds_back_to_the_original_state() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.player.energy = 400;
+    state.opponent.energy = 400;
+    state.player.iron_curtain_available = true;
+    let start = state.clone();
+
+    let moves = [
+        (Command::Build(Point::new(7, 2), BuildingType::Attack), Command::Build(Point::new(7, 2), BuildingType::Tesla)),
+        (Command::Build(Point::new(0, 2), BuildingType::Defence), Command::Build(Point::new(1, 2), BuildingType::Energy)),
+        (Command::IronCurtain, Command::Nothing),
+        (Command::Deconstruct(Point::new(0, 2)), Command::Build(Point::new(6, 2), BuildingType::Attack)),
+        (Command::Nothing, Command::Nothing),
+        (Command::Nothing, Command::Nothing)
+    ];
+    let mut undos = Vec::new();
+    let mut states = vec!(state.clone());
+    for &(player_command, opponent_command) in moves.iter().chain(std::iter::repeat_n(&(Command::Nothing, Command::Nothing), 20)) {
+        let (status, undo) = state.simulate_with_undo(player_command, opponent_command);
+        assert_eq!(status, state.status);
+        undos.push(undo);
+        states.push(state.clone());
+    }
+
+    while let Some(undo) = undos.pop() {
+        assert_eq!(state, states.pop().unwrap());
+        state.unsimulate(undo);
+    }
+    assert_eq!(state, start);
+}
+
+fn assert_hash_follows_random_game<M: MapSize>() {
+    let mut rng = XorShiftRng::from_seed(INIT_SEED);
+    let mut state = tournament::random_opening::<M>(GameConfig::default(), 0, INIT_SEED);
+    let mut undos = Vec::new();
+    while state.status == GameStatus::Continue && state.round < 200 {
+        let player_command = monte_carlo::random_move(&state.player, &state.opponent, &state.config, &RandomMoveOptions::default(), &mut rng);
+        let opponent_command = monte_carlo::random_move(&state.opponent, &state.player, &state.config, &RandomMoveOptions::default(), &mut rng);
+        let before = state.hash();
+        let (_, undo) = state.simulate_with_undo(player_command, opponent_command);
+        undos.push((before, undo));
+
+        assert_eq!(state.hash(), state.zobrist_hash());
+        assert_eq!(state.hash(), state.sorted().zobrist_hash());
+        assert_ne!(state.hash(), before);
+    }
+
+    while let Some((before, undo)) = undos.pop() {
+        state.unsimulate(undo);
+        assert_eq!(state.hash(), before);
+    }
+    assert_eq!(state.hash(), state.zobrist_hash());
+}
+
+#[test]
+fn it_updates_the_hash_along_with_the_state() {
+    assert_hash_follows_random_game::<Map16x8>();
+    assert_hash_follows_random_game::<Map20x10>();
+}
+
+#[test]
+fn it_gives_the_same_hash_to_moves_played_in_a_different_order() {
+    let mut start = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    start.player.energy = 100;
+    start.opponent.energy = 100;
+    let first = Command::Build(Point::new(0, 0), BuildingType::Defence);
+    let second = Command::Build(Point::new(0, 1), BuildingType::Defence);
+    let play = |moves: &[(Command, Command)]| {
+        let mut state = start.clone();
+        for &(player_command, opponent_command) in moves.iter().chain(std::iter::repeat_n(&(Command::Nothing, Command::Nothing), 4)) {
+            state.simulate(player_command, opponent_command);
+        }
+        state
+    };
+
+    let state = play(&[(first, Command::Nothing), (second, Command::Nothing)]);
+    let swapped = play(&[(second, Command::Nothing), (first, Command::Nothing)]);
+    let mirrored = play(&[(Command::Nothing, first), (Command::Nothing, second)]);
+
+    assert_eq!(state.sorted(), swapped.sorted());
+    assert_eq!(state.zobrist_hash(), swapped.zobrist_hash());
+    assert_ne!(state.zobrist_hash(), mirrored.zobrist_hash());
+    assert_ne!(state.zobrist_hash(), start.zobrist_hash());
+}
+
+#[test]
+fn it_gives_the_same_hash_to_teslas_of_different_ages() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.player.energy = 1000;
+    state.simulate(Command::Build(Point::new(7, 0), BuildingType::Tesla), Command::Nothing);
+    while state.player.tesla_cooldowns.is_empty() {
+        state.simulate(Command::Nothing, Command::Nothing);
+    }
+    let mut older = state.clone();
+    older.player.tesla_cooldowns[0].age += 10;
+
+    assert_ne!(state, older);
+    assert_eq!(state.zobrist_hash(), older.zobrist_hash());
+}
+
+#[test]
+fn it_keeps_the_teslas_in_the_same_order_whichever_was_built_first() {
+    let mut start = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    // Only enough energy to build them, so that neither fires
+    start.player.energy = 2 * start.config.tesla_price;
+    let first = Command::Build(Point::new(7, 3), BuildingType::Tesla);
+    let second = Command::Build(Point::new(6, 1), BuildingType::Tesla);
+    let play = |moves: [Command; 2]| {
+        let mut state = start.clone();
+        for &command in moves.iter().chain(std::iter::repeat_n(&Command::Nothing, start.config.tesla_construction_time as usize + 2)) {
+            state.simulate(command, Command::Nothing);
+        }
+        state
+    };
+
+    let state = play([first, second]);
+    let reversed = play([second, first]);
+    let positions = |state: &BitwiseGameState| state.player.tesla_cooldowns.iter().map(|t| t.pos).collect::<Vec<_>>();
+
+    assert_eq!(positions(&state), positions(&reversed));
+    assert_eq!(state.zobrist_hash(), reversed.zobrist_hash());
+    assert_eq!(json::state_from_str::<Map16x8>(&json::state_to_string(&state).unwrap()).unwrap().player.tesla_cooldowns.iter().map(|t| t.pos).collect::<Vec<_>>(), positions(&state));
+}
+
+fn every_command<M: MapSize>() -> Vec<Command> {
+    let mut commands = vec!(Command::Nothing, Command::IronCurtain);
+    for x in 0..M::SINGLE_MAP_WIDTH + 1 {
+        for y in 0..M::MAP_HEIGHT {
+            let p = Point::new(x, y);
+            commands.push(Command::Deconstruct(p));
+            commands.extend(BuildingType::all().iter().map(|&b| Command::Build(p, b)));
+        }
+    }
+    commands
+}
+
+fn assert_legal_commands_match_validation<M: MapSize>() {
+    let mut rng = XorShiftRng::from_seed(INIT_SEED);
+    let mut state = tournament::random_opening::<M>(GameConfig::default(), 0, INIT_SEED);
+    let every_command = every_command::<M>();
+    while state.status == GameStatus::Continue && state.round < 200 {
+        for player in &[&state.player, &state.opponent] {
+            let legal = player.legal_commands(&state.config);
+            assert_eq!(legal.iter().count(), legal.count());
+            for &command in &every_command {
+                assert_eq!(legal.contains(command), player.validate_command(command, &state.config).is_ok(), "{}", command);
+            }
+            assert!(legal.iter().all(|command| player.validate_command(command, &state.config).is_ok()));
+
+            let sensible = player.sensible_commands(&state.config, true);
+            assert!(sensible.iter().all(|command| legal.contains(command)));
+        }
+
+        let player_command = monte_carlo::random_move(&state.player, &state.opponent, &state.config, &RandomMoveOptions::default(), &mut rng);
+        let opponent_command = monte_carlo::random_move(&state.opponent, &state.player, &state.config, &RandomMoveOptions::default(), &mut rng);
+        state.simulate(player_command, opponent_command);
+    }
+}
+
+#[test]
+fn it_lists_exactly_the_commands_that_are_valid() {
+    assert_legal_commands_match_validation::<Map16x8>();
+    assert_legal_commands_match_validation::<Map20x10>();
+}
+
+fn assert_swapped_game_mirrors_game<M: MapSize>() {
+    let mut rng = XorShiftRng::from_seed(INIT_SEED);
+    let mut state = tournament::random_opening::<M>(GameConfig::default(), 0, INIT_SEED);
+    let mut swapped = state.swapped();
+    while state.status == GameStatus::Continue && state.round < 200 {
+        let player_command = monte_carlo::random_move(&state.player, &state.opponent, &state.config, &RandomMoveOptions::default(), &mut rng);
+        let opponent_command = monte_carlo::random_move(&state.opponent, &state.player, &state.config, &RandomMoveOptions::default(), &mut rng);
+        let status = state.simulate(player_command, opponent_command);
+        let swapped_status = swapped.simulate(opponent_command, player_command);
+
+        assert_eq!(swapped_status, status.swapped());
+        assert_eq!(swapped, state.swapped());
+        assert_eq!(swapped.swapped(), state);
+    }
+}
+
+#[test]
+fn it_plays_the_same_game_from_the_opponents_side() {
+    assert_swapped_game_mirrors_game::<Map16x8>();
+    assert_swapped_game_mirrors_game::<Map20x10>();
+}
+
+#[test]
+fn it_swaps_the_winner_along_with_the_players() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.opponent.health = 0;
+    state.simulate(Command::Nothing, Command::Nothing);
+    assert_eq!(state.status, GameStatus::PlayerWon);
+
+    let swapped = state.swapped();
+    assert_eq!(swapped.status, GameStatus::OpponentWon);
+    assert_eq!(swapped.player, state.opponent);
+    assert_eq!(swapped.opponent, state.player);
+}
+
+#[test]
+fn it_explains_why_a_command_is_invalid() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.player.energy = 100;
+    state.simulate(Command::Build(Point::new(0, 0), BuildingType::Tesla), Command::Nothing);
+    state.player.energy = 30;
+    let player = &state.player;
+    let config = &state.config;
+
+    assert_eq!(player.validate_command(Command::Build(Point::new(1, 0), BuildingType::Attack), config), Ok(()));
+    assert_eq!(player.validate_command(Command::Build(Point::new(8, 0), BuildingType::Attack), config), Err(CommandError::OutOfBounds(Point::new(8, 0))));
+    assert_eq!(player.validate_command(Command::Build(Point::new(0, 0), BuildingType::Attack), config), Err(CommandError::CellOccupied(Point::new(0, 0))));
+    assert_eq!(player.validate_command(Command::Build(Point::new(1, 0), BuildingType::Tesla), config), Err(CommandError::NotEnoughEnergy { price: config.tesla_price, energy: 30 }));
+    assert_eq!(player.validate_command(Command::Deconstruct(Point::new(1, 0)), config), Err(CommandError::NothingToDeconstruct(Point::new(1, 0))));
+    assert_eq!(player.validate_command(Command::Deconstruct(Point::new(0, 0)), config), Ok(()));
+    assert_eq!(player.validate_command(Command::IronCurtain, config), Err(CommandError::IronCurtainUnavailable));
+}
+
+#[test]
+fn it_builds_every_round_with_the_longest_construction_time_it_reads() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.config.defence_construction_time = 3;
+    state.config.tesla_construction_time = 20;
+    let path = env::temp_dir().join(format!("zombot-construction-{}.json", process::id())).to_string_lossy().into_owned();
+    json::write_bitwise_state_to_file(&state, &path).unwrap();
+    let mut state = json::read_bitwise_state_from_file(&path).unwrap();
+
+    state.player.energy = 10000;
+    state.simulate(Command::Build(Point::new(7, 0), BuildingType::Tesla), Command::Nothing);
+    state.simulate(Command::Build(Point::new(7, 1), BuildingType::Tesla), Command::Nothing);
+    for x in 0..7 {
+        let command = Command::Build(Point::new(x, 0), BuildingType::Defence);
+        assert_eq!(state.player.validate_command(command, &state.config), Ok(()));
+        state.simulate(command, Command::Nothing);
+    }
+    assert_eq!(state.player.unconstructed.len(), 2 + 3);
+
+    state.config.defence_construction_time = 4;
+    json::write_bitwise_state_to_file(&state, &path).unwrap();
+    let result = json::read_bitwise_state_from_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
+
+#[test]
+fn it_ignores_invalid_commands_like_the_game_engine() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.player.energy = 10;
+    let mut expected = state.clone();
+
+    let (status, player_result, opponent_result) = state.try_simulate(Command::Build(Point::new(0, 0), BuildingType::Energy), Command::Build(Point::new(0, 0), BuildingType::Energy));
+    expected.simulate(Command::Nothing, Command::Build(Point::new(0, 0), BuildingType::Energy));
+
+    assert_eq!(status, GameStatus::Continue);
+    assert_eq!(player_result, Err(CommandError::NotEnoughEnergy { price: state.config.energy_price, energy: 10 }));
+    assert_eq!(opponent_result, Ok(()));
+    assert_eq!(state, expected);
+}
+
+#[test]
+fn it_formats_deconstruct_as_action_type_3() {
+    assert_eq!(format!("{}", Command::Deconstruct(Point::new(3, 4))), "3,4,3");
+}
+
+#[test]
+fn it_reads_the_game_rules_from_game_details() {
+    let state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+
+    assert_eq!(state.config.max_rounds, 400);
+    assert_eq!(state.config.tesla_price, 100);
+    assert_eq!(state.config.defence_construction_time, 3);
+    assert_eq!(state.config.energy_generated_tower, 3);
+    assert_eq!(state.config.iron_curtain_price, 150);
+    assert_eq!(state.config.iron_curtain_unlock_interval, 50);
+}
+
+fn new_20x10_game() -> BitwiseGameState<Map20x10> {
+    let mut player = Player::empty();
+    player.health = 100;
+    player.energy = 1000;
+    let mut opponent = Player::empty();
+    opponent.health = 100;
+    opponent.energy = 1000;
+    BitwiseGameState::new(player, opponent, 0, GameConfig::default())
+}
+
+#[test]
+fn it_fires_missiles_across_a_20x10_map() {
+    let mut state = new_20x10_game();
+    assert_eq!(state.player.unoccupied_cell_count(), 100);
+
+    state.simulate(Command::Build(Point::new(9, 9), BuildingType::Attack), Command::Nothing);
+    for _ in 0..20 {
+        state.simulate(Command::Nothing, Command::Nothing);
+    }
+
+    assert!(state.opponent.health < 100);
+    assert_eq!(state.player.health, 100);
+}
+
+#[test]
+fn it_limits_the_tesla_range_on_a_20x10_map() {
+    let mut state = new_20x10_game();
+    let out_of_range = Point::new(0, 5);
+    let in_range = Point::new(1, 5);
+
+    state.simulate(Command::Build(Point::new(9, 5), BuildingType::Tesla), Command::Build(out_of_range, BuildingType::Energy));
+    state.simulate(Command::Nothing, Command::Build(in_range, BuildingType::Energy));
+    for _ in 0..20 {
+        state.simulate(Command::Nothing, Command::Nothing);
+    }
+
+    assert_eq!(state.opponent.energy_towers & in_range.to_either_bitfield::<Map20x10>(), 0);
+    assert_ne!(state.opponent.energy_towers & out_of_range.to_either_bitfield::<Map20x10>(), 0);
+    assert_eq!(state.opponent.health, 100);
+}
+
+#[test]
+fn it_blocks_missiles_with_defence_on_a_20x10_map() {
+    let mut state = new_20x10_game();
+
+    state.simulate(Command::Build(Point::new(0, 9), BuildingType::Attack), Command::Build(Point::new(9, 9), BuildingType::Defence));
+    for _ in 0..20 {
+        state.simulate(Command::Nothing, Command::Nothing);
+    }
+
+    assert_eq!(state.opponent.health, 100);
+    assert_eq!(state.status, GameStatus::Continue);
+}
+
+#[test]
+fn it_finds_free_cells_on_a_20x10_map() {
+    let mut state = new_20x10_game();
+    state.simulate(Command::Build(Point::new(0, 0), BuildingType::Energy), Command::Nothing);
+
+    assert_eq!(state.player.unoccupied_cell_count(), 99);
+    assert_eq!(state.player.location_of_unoccupied_cell(0), Point::new(1, 0));
+    assert_eq!(state.player.location_of_unoccupied_cell(98), Point::new(9, 9));
+    assert_eq!(state.player.location_of_occupied_cell(0), Point::new(0, 0));
+}
+
+#[test]
+fn it_draws_buildings_and_missiles_on_the_board() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.player.energy = 100;
+    state.opponent.energy = 100;
+    state.simulate(Command::Build(Point::new(0, 0), BuildingType::Attack), Command::Build(Point::new(1, 1), BuildingType::Defence));
+    state.simulate(Command::Nothing, Command::Nothing);
+
+    let drawn = state.to_string();
+    let lines: Vec<&str> = drawn.lines().collect();
+    assert_eq!(lines.len(), 3 + 8);
+    assert_eq!(lines[0], "Round 2 (Continue)");
+    assert_eq!(&lines[3][..6], "A3 .  ");
+    assert_eq!(&lines[4][14*3..], "d1 .  ");
+    assert!(lines[3].contains('>'));
+    assert!(!format!("{:#}", state).lines().nth(3).unwrap().starts_with('A'));
+}
@@ -0,0 +1,115 @@
+extern crate zombot;
+#[macro_use] extern crate proptest;
+
+use proptest::prelude::*;
+
+use zombot::input::json;
+use zombot::arena::tournament;
+use zombot::engine::bitwise_engine::BitwiseGameState;
+use zombot::engine::bitboard::{MapSize, Map16x8, Map20x10};
+use zombot::engine::config::GameConfig;
+use zombot::engine::status::GameStatus;
+use zombot::engine::command::{Command, BuildingType};
+use zombot::engine::geometry::Point;
+
+use std::env;
+use std::fs;
+use std::thread;
+
+const STATE_PATH: &str = "tests/state0.json";
+
+fn temp_state_path() -> String {
+    let name = format!("zombot-json-test-{:?}.json", thread::current().id());
+    env::temp_dir().join(name).to_string_lossy().into_owned()
+}
+
+fn assert_round_trips<M: MapSize>(state: &BitwiseGameState<M>) -> Result<(), TestCaseError> {
+    let path = temp_state_path();
+    json::write_state_to_file(state, &path).unwrap();
+    let read_state = json::read_state_from_file::<M>(&path).unwrap();
+
+    prop_assert_eq!(read_state.sorted(), state.sorted());
+    Ok(())
+}
+
+fn arb_config() -> impl Strategy<Value = GameConfig> {
+    (
+        (50u16..400, 1u16..10, 1u16..10, 1u8..10),
+        (1u16..100, 1u8..4, 1u16..100, 1u8..4, 1u16..100, 1u8..4),
+        (1u16..200, 1u8..12, 1u8..20, 1u8..30),
+        (50u16..200, 10u16..60, 1u8..10),
+        (1u16..200, 1u8..30, 0u16..50)
+    ).prop_map(|(basics, prices, tesla, iron_curtain, energy_costs)| {
+        let (max_rounds, energy_generated_base, energy_generated_tower, missile_damage) = basics;
+        let (missile_price, missile_construction_time, defence_price, defence_construction_time, energy_price, energy_construction_time) = prices;
+        let (tesla_price, tesla_construction_time, tesla_cooldown, tesla_damage) = tesla;
+        let (iron_curtain_price, iron_curtain_unlock_interval, iron_curtain_duration) = iron_curtain;
+        let (tesla_firing_energy, tesla_range, deconstruct_energy) = energy_costs;
+        GameConfig {
+            max_rounds,
+            energy_generated_base, energy_generated_tower,
+            missile_damage, missile_price, missile_construction_time,
+            defence_price, defence_construction_time,
+            energy_price, energy_construction_time,
+            tesla_price, tesla_construction_time, tesla_cooldown, tesla_damage, tesla_firing_energy, tesla_range,
+            iron_curtain_price, iron_curtain_unlock_interval, iron_curtain_duration,
+            deconstruct_energy
+        }
+    })
+}
+
+proptest! {
+    #[test]
+    fn it_round_trips_random_games_on_a_16x8_map(config in arb_config(), seed in any::<u64>(), rounds in 0u16..150) {
+        let state = tournament::random_opening::<Map16x8>(config, rounds, tournament::seed_for_game(seed, 0));
+        prop_assume!(state.status == GameStatus::Continue);
+        assert_round_trips(&state)?;
+    }
+
+    #[test]
+    fn it_round_trips_random_games_on_a_20x10_map(config in arb_config(), seed in any::<u64>(), rounds in 0u16..150) {
+        let state = tournament::random_opening::<Map20x10>(config, rounds, tournament::seed_for_game(seed, 0));
+        prop_assume!(state.status == GameStatus::Continue);
+        assert_round_trips(&state)?;
+    }
+}
+
+#[test]
+fn it_round_trips_the_state_from_the_game_engine() {
+    let state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let path = temp_state_path();
+    json::write_bitwise_state_to_file(&state, &path).unwrap();
+
+    assert_eq!(json::read_bitwise_state_from_file(&path).unwrap(), state);
+}
+
+#[test]
+fn it_rejects_a_state_with_too_many_teslas() {
+    let mut state = json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    state.player.energy = 1000;
+    state.simulate(Command::Build(Point::new(7, 0), BuildingType::Tesla), Command::Nothing);
+    state.simulate(Command::Build(Point::new(7, 1), BuildingType::Tesla), Command::Nothing);
+    state.simulate(Command::Build(Point::new(0, 0), BuildingType::Energy), Command::Nothing);
+    for _ in 0..state.config.tesla_construction_time {
+        state.simulate(Command::Nothing, Command::Nothing);
+    }
+    let written = json::state_to_string(&state).unwrap();
+    assert!(json::state_from_str::<Map16x8>(&written).is_ok());
+
+    let three_teslas = written.replace("\"buildingType\":\"ENERGY\"", "\"buildingType\":\"TESLA\"");
+    assert_ne!(three_teslas, written);
+    assert!(json::state_from_str::<Map16x8>(&three_teslas).is_err());
+}
+
+#[test]
+fn it_reads_the_building_prices_when_there_are_no_building_stats() {
+    let content = fs::read_to_string(STATE_PATH).unwrap();
+    let stats_start = content.find(",\"buildingsStats\"").unwrap();
+    let stats_end = content.find(",\"ironCurtainStats\"").unwrap();
+    let without_stats = format!("{}{}", &content[..stats_start], &content[stats_end..])
+        .replace("\"buildingPrices\":{\"TESLA\":100,\"DEFENSE\":30,\"ATTACK\":30,\"ENERGY\":20}", "\"buildingPrices\":{\"TESLA\":150,\"DEFENSE\":35,\"ATTACK\":40,\"ENERGY\":25}");
+
+    let state = json::state_from_str::<Map16x8>(&without_stats).unwrap();
+
+    assert_eq!((state.config.tesla_price, state.config.defence_price, state.config.missile_price, state.config.energy_price), (150, 35, 40, 25));
+}
@@ -1,8 +1,29 @@
 extern crate zombot;
 extern crate time;
+extern crate rayon;
+extern crate rand;
 use time::{PreciseTime, Duration};
 
 use zombot::*;
+use zombot::engine::constants::*;
+use zombot::strategy::config::{BotConfig, StrategyType};
+use zombot::strategy::monte_carlo::{MonteCarloOptions, RandomMoveOptions};
+use zombot::strategy::decision_log::{DecisionRecord, CandidateRecord};
+use zombot::strategy::heatmap;
+use zombot::strategy::budget::SearchBudget;
+use zombot::engine::bitboard::Map16x8;
+use zombot::engine::geometry::Point;
+
+use zombot::strategy::monte_carlo_tree;
+use zombot::engine::command::Command;
+use zombot::engine::status::GameStatus;
+
+use rand::{XorShiftRng, SeedableRng};
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
 
 const STATE_PATH: &str = "tests/state0.json";
 
@@ -15,7 +36,7 @@ fn it_does_a_normal_turn_successfully() {
         Err(error) => panic!("Error while parsing JSON file: {}", error)
     };
     let max_time = Duration::milliseconds(200);
-    strategy::monte_carlo::choose_move(&state, start_time, max_time);
+    strategy::monte_carlo::choose_move(&state, start_time, max_time, &MonteCarloOptions::default());
 
     assert!(start_time.to(PreciseTime::now()) < max_time + Duration::milliseconds(50))
 }
@@ -28,7 +49,283 @@ fn it_does_a_normal_tree_serach_turn_successfully() {
         Err(error) => panic!("Error while parsing JSON file: {}", error)
     };
     let max_time = Duration::milliseconds(200);
-    strategy::monte_carlo_tree::choose_move(&state, start_time, max_time);
+    strategy::monte_carlo_tree::choose_move(&state, start_time, max_time, &RandomMoveOptions::default());
 
     assert!(start_time.to(PreciseTime::now()) < max_time + Duration::milliseconds(50))
 }
+
+#[test]
+fn it_does_a_normal_turn_with_each_strategy_and_options() {
+    let state = match input::json::read_bitwise_state_from_file(STATE_PATH) {
+        Ok(ok) => ok,
+        Err(error) => panic!("Error while parsing JSON file: {}", error)
+    };
+    let max_time = Duration::milliseconds(50);
+    let default = BotConfig::default();
+    let flipped = BotConfig {
+        strategy: StrategyType::MonteCarlo,
+        static_opening: !default.static_opening,
+        energy_cutoff: !default.energy_cutoff,
+        discard_poor_performers: !default.discard_poor_performers,
+        heuristic_random: !default.heuristic_random,
+        weighted_win_ratio: !default.weighted_win_ratio,
+        decision_log: !default.decision_log,
+        budget: Some(SearchBudget::RolloutsPerCandidate(2)),
+        trees: Some(2),
+        max_nodes: Some(500),
+        reuse_tree: !default.reuse_tree
+    };
+    let decision_folder = env::temp_dir().join(format!("zombot-decisions-{}", process::id()));
+
+    for &strategy in &[StrategyType::MonteCarlo, StrategyType::MonteCarloTree, StrategyType::Random] {
+        for &config in &[default, flipped] {
+            let start_time = PreciseTime::now();
+            let command = BotConfig { strategy, ..config }
+                .build_strategy(INIT_SEED, &decision_folder)
+                .choose_move(&state, start_time, max_time);
+            match config.budget {
+                Some(budget) if !budget.is_time() => assert_eq!(state.player.validate_command(command, &state.config), Ok(())),
+                _ => assert!(start_time.to(PreciseTime::now()) < max_time + Duration::milliseconds(50))
+            }
+        }
+    }
+    let _ = fs::remove_dir_all(&decision_folder);
+}
+
+#[test]
+fn it_logs_the_scores_and_pruned_candidates_for_a_decision() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let decision_folder = env::temp_dir().join(format!("zombot-decision-log-{}", process::id()));
+    let config = BotConfig { static_opening: false, decision_log: true, ..BotConfig::default() };
+
+    let command = config.build_strategy(INIT_SEED, &decision_folder)
+        .choose_move(&state, PreciseTime::now(), Duration::milliseconds(100));
+    let record = input::json::read_decision_record_from_file(&decision_folder.join("Round 000").join("decision.json").to_string_lossy()).unwrap();
+    fs::remove_dir_all(&decision_folder).unwrap();
+
+    assert_eq!(record.round, 0);
+    assert!(record.weighted_win_ratio);
+    assert_eq!(record.choice, input::command::format_replay_command(command));
+    assert!(record.candidates.iter().all(|c| c.attempts == c.victories + c.defeats + c.draws + c.stalemates));
+
+    // Each phase keeps the better half, and the pruned ones are left at
+    // the end in the order they were dropped
+    let count = record.candidates.len();
+    assert_eq!(record.pruned.len(), 2);
+    assert_eq!(record.pruned[0].len(), count - count / 2);
+    assert_eq!(record.pruned[1].len(), count / 2 - count / 4);
+    let pruned: Vec<&String> = record.pruned.iter().rev().flat_map(|phase| phase.iter()).collect();
+    let last: Vec<&String> = record.candidates[count / 4..].iter().map(|c| &c.command).collect();
+    assert_eq!(pruned, last);
+    let kept_attempts = record.candidates[0].attempts;
+    assert!(record.candidates[count - 1].attempts < kept_attempts);
+}
+
+#[test]
+fn it_makes_the_same_decision_every_time_with_a_rollout_budget() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let decision_folder = env::temp_dir().join(format!("zombot-reproducible-{}", process::id()));
+    let config = BotConfig { static_opening: false, decision_log: true, budget: Some(SearchBudget::RolloutsPerCandidate(6)), ..BotConfig::default() };
+    let seed = strategy::seed_from_u64(42);
+
+    let decide = || {
+        let command = config.build_strategy(seed, &decision_folder)
+            .choose_move(&state, PreciseTime::now(), Duration::milliseconds(0));
+        let record = input::json::read_decision_record_from_file(&decision_folder.join("Round 000").join("decision.json").to_string_lossy()).unwrap();
+        (command, record)
+    };
+    let (command, record) = decide();
+    let (single_thread_command, single_thread_record) = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap().install(decide);
+    fs::remove_dir_all(&decision_folder).unwrap();
+
+    assert_eq!(command, single_thread_command);
+    assert_eq!(record, single_thread_record);
+    assert_eq!(record.candidates.iter().map(|c| c.attempts).max(), Some(6));
+}
+
+#[test]
+fn it_makes_the_same_tree_search_decision_every_time_with_a_rollout_budget() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let config = BotConfig { strategy: StrategyType::MonteCarloTree, static_opening: false, budget: Some(SearchBudget::Rollouts(500)), ..BotConfig::default() };
+
+    let decide = |seed| config.build_strategy(seed, Path::new("decisions"))
+        .choose_move(&state, PreciseTime::now(), Duration::milliseconds(0));
+
+    let command = decide(strategy::seed_from_u64(7));
+    let single_thread_command = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap().install(|| decide(strategy::seed_from_u64(7)));
+
+    assert_eq!(command, decide(strategy::seed_from_u64(7)));
+    assert_eq!(command, single_thread_command);
+}
+
+#[test]
+fn it_keeps_searching_when_the_tree_is_out_of_nodes() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let config = BotConfig { strategy: StrategyType::MonteCarloTree, static_opening: false, budget: Some(SearchBudget::Rollouts(3000)), trees: Some(2), max_nodes: Some(200), ..BotConfig::default() };
+
+    let decide = || config.build_strategy(INIT_SEED, Path::new("decisions"))
+        .choose_move(&state, PreciseTime::now(), Duration::milliseconds(0));
+
+    assert_eq!(decide(), decide());
+}
+
+#[test]
+fn it_spends_a_node_budget_when_the_trees_cant_grow() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let last_round = {
+        let mut last_round = state.clone();
+        last_round.round = last_round.config.max_rounds - 1;
+        last_round
+    };
+
+    for state in &[&state, &last_round] {
+        for &max_nodes in &[1, 4] {
+            let config = BotConfig { strategy: StrategyType::MonteCarloTree, static_opening: false, budget: Some(SearchBudget::Nodes(500)), trees: Some(2), max_nodes: Some(max_nodes), ..BotConfig::default() };
+            let command = config.build_strategy(INIT_SEED, Path::new("decisions"))
+                .choose_move(state, PreciseTime::now(), Duration::milliseconds(0));
+            assert_eq!(state.player.validate_command(command, &state.config), Ok(()));
+        }
+    }
+}
+
+#[test]
+fn it_stops_after_the_total_rollout_budget() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let decision_folder = env::temp_dir().join(format!("zombot-rollout-budget-{}", process::id()));
+    let config = BotConfig { static_opening: false, discard_poor_performers: false, decision_log: true, budget: Some(SearchBudget::Rollouts(1000)), ..BotConfig::default() };
+
+    config.build_strategy(INIT_SEED, &decision_folder)
+        .choose_move(&state, PreciseTime::now(), Duration::milliseconds(0));
+    let record = input::json::read_decision_record_from_file(&decision_folder.join("Round 000").join("decision.json").to_string_lossy()).unwrap();
+    fs::remove_dir_all(&decision_folder).unwrap();
+
+    // Every option plays a game in each round, so the budget can be
+    // overshot by less than a round
+    let rollouts: u32 = record.candidates.iter().map(|c| c.attempts).sum();
+    assert!(rollouts >= 1000);
+    assert!(rollouts < 1000 + record.candidates.len() as u32);
+}
+
+#[test]
+fn it_carries_on_from_the_saved_tree_after_the_moves_it_searched() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let decision_folder = env::temp_dir().join(format!("zombot-tree-reuse-{}", process::id()));
+    let tree_file = decision_folder.join(monte_carlo_tree::TREE_FILE);
+    let config = BotConfig {
+        strategy: StrategyType::MonteCarloTree,
+        static_opening: false,
+        budget: Some(SearchBudget::Rollouts(2000)),
+        trees: Some(1),
+        reuse_tree: true,
+        ..BotConfig::default()
+    };
+
+    let mut bot = config.build_strategy(INIT_SEED, &decision_folder);
+    let command = bot.choose_move(&state, PreciseTime::now(), Duration::milliseconds(0));
+
+    let mut rng = XorShiftRng::from_seed(INIT_SEED);
+    let next_states: Vec<_> = (0..50).map(|_| {
+        let opponent_command = strategy::monte_carlo::random_move(&state.opponent, &state.player, &state.config, &RandomMoveOptions::default(), &mut rng);
+        let mut next_state = state.clone();
+        next_state.simulate(command, opponent_command);
+        next_state
+    }).collect();
+    let saved_rollouts: Vec<u32> = next_states.iter()
+        .map(|next_state| monte_carlo_tree::saved_tree_rollouts(&tree_file, next_state).unwrap())
+        .collect();
+    let two_rounds_later = {
+        let mut later = next_states[0].clone();
+        later.simulate(Command::Nothing, Command::Nothing);
+        later
+    };
+    let stale_rollouts = monte_carlo_tree::saved_tree_rollouts(&tree_file, &two_rounds_later).unwrap();
+
+    let reused = next_states.iter().zip(saved_rollouts.iter()).find(|&(_, &rollouts)| rollouts > 0).map(|(s, _)| s.clone()).unwrap();
+    bot.choose_move(&reused, PreciseTime::now(), Duration::milliseconds(0));
+    fs::remove_dir_all(&decision_folder).unwrap();
+
+    assert_eq!(stale_rollouts, 0);
+    assert!(saved_rollouts.iter().all(|&rollouts| rollouts < 2000));
+}
+
+#[test]
+fn it_stops_searching_when_the_game_is_over_or_in_its_last_round() {
+    let state = input::json::read_bitwise_state_from_file(STATE_PATH).unwrap();
+    let finished = {
+        let mut finished = state.clone();
+        finished.opponent.health = 0;
+        finished.status = GameStatus::PlayerWon;
+        finished
+    };
+    let last_round = {
+        let mut last_round = state.clone();
+        last_round.round = last_round.config.max_rounds - 1;
+        last_round
+    };
+    let out_of_rounds = {
+        let mut out_of_rounds = state.clone();
+        out_of_rounds.round = out_of_rounds.config.max_rounds;
+        out_of_rounds
+    };
+    let budgets = [SearchBudget::Rollouts(200), SearchBudget::RolloutsPerCandidate(2), SearchBudget::Nodes(200)];
+
+    for &strategy in &[StrategyType::MonteCarlo, StrategyType::MonteCarloTree] {
+        for &budget in &budgets {
+            for state in &[&finished, &last_round, &out_of_rounds] {
+                let config = BotConfig { strategy, static_opening: false, budget: Some(budget), ..BotConfig::default() };
+                let command = config.build_strategy(INIT_SEED, Path::new("decisions"))
+                    .choose_move(state, PreciseTime::now(), Duration::milliseconds(0));
+                assert_eq!(state.player.validate_command(command, &state.config), Ok(()));
+            }
+        }
+    }
+}
+
+#[test]
+fn it_parses_search_budgets_from_the_command_line() {
+    assert_eq!("time:1950".parse::<SearchBudget>().unwrap(), SearchBudget::TimeMillis(1950));
+    assert_eq!("rollouts:20000".parse::<SearchBudget>().unwrap(), SearchBudget::Rollouts(20000));
+    assert_eq!("rollouts-per-candidate:50".parse::<SearchBudget>().unwrap(), SearchBudget::RolloutsPerCandidate(50));
+    assert_eq!("nodes:3000".parse::<SearchBudget>().unwrap(), SearchBudget::Nodes(3000));
+    assert_eq!(SearchBudget::Nodes(3000).to_string(), "nodes:3000");
+    assert!("rollouts".parse::<SearchBudget>().is_err());
+    assert!("moves:10".parse::<SearchBudget>().is_err());
+}
+
+#[test]
+fn it_fills_in_missing_bot_config_with_defaults() {
+    let config = input::json::read_bot_config_from_file("tests/bot-config-tree.json").unwrap();
+
+    assert_eq!(config, BotConfig {
+        strategy: StrategyType::MonteCarloTree,
+        heuristic_random: true,
+        budget: Some(SearchBudget::Nodes(2000)),
+        ..BotConfig::default()
+    });
+}
+
+#[test]
+fn it_draws_a_heatmap_for_each_building_type_from_a_decision_log() {
+    let record = DecisionRecord {
+        round: 3,
+        candidates: vec!(
+            CandidateRecord { command: String::from("1,2,2"), win_ratio: 600, ..CandidateRecord::default() },
+            CandidateRecord { command: String::from("0,0,1"), win_ratio: -200, ..CandidateRecord::default() },
+            CandidateRecord { command: String::from("1,2,2"), win_ratio: 100, ..CandidateRecord::default() },
+            CandidateRecord { command: String::from(""), win_ratio: 50, ..CandidateRecord::default() }
+        ),
+        ..DecisionRecord::default()
+    };
+
+    let heatmaps = heatmap::decision_heatmaps::<Map16x8>(&record).unwrap();
+
+    assert_eq!(heatmaps.iter().map(|h| h.label()).collect::<Vec<_>>(), vec!("ENERGY", "ATTACK", "DEFENCE", "TESLA"));
+    assert_eq!(heatmaps[0].get(Point::new(1, 2)), Some(600));
+    assert_eq!(heatmaps[1].get(Point::new(0, 0)), Some(-200));
+    assert_eq!(heatmaps[2].get(Point::new(0, 0)), None);
+    assert_eq!(heatmap::csv_rows(record.round, &heatmaps), "3,ENERGY,1,2,600\n3,ATTACK,0,0,-200\n");
+
+    let ppm = heatmap::to_ppm(&heatmaps);
+    assert!(ppm.starts_with(b"P6\n"));
+    assert!(heatmap::to_svg(&heatmaps).contains("<title>1,2: 600</title>"));
+}
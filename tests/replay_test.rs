@@ -0,0 +1,211 @@
+extern crate zombot;
+extern crate time;
+extern crate rand;
+
+use time::Duration;
+use rand::{XorShiftRng, SeedableRng};
+
+use zombot::arena::bot::Bot;
+use zombot::arena::referee;
+use zombot::input::{json, command};
+use zombot::replay::recorder::{self, ReplayRecorder};
+use zombot::replay::import;
+use zombot::replay::viewer::Replay;
+use zombot::replay::divergence::{self, Difference};
+use zombot::strategy::monte_carlo::RandomMoveOptions;
+use zombot::engine::config::GameConfig;
+use zombot::engine::command::{Command, BuildingType};
+use zombot::engine::geometry::Point;
+use zombot::engine::constants::*;
+use zombot::engine::bitboard::{MapSize, Map16x8, Map20x10};
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn scratch_folder(name: &str) -> PathBuf {
+    let folder = env::temp_dir().join(format!("zombot-replay-test-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&folder);
+    folder
+}
+
+#[test]
+fn it_records_a_match_that_simulates_the_same_way() {
+    let folder = scratch_folder("match");
+    let state = referee::initial_state::<Map16x8>(GameConfig::default());
+    let random: Bot = "random".parse().unwrap();
+    let mut recorder = ReplayRecorder::create(&folder).unwrap();
+    let result = referee::play_match(state, &random, &random, Duration::milliseconds(0), INIT_SEED, Some(&mut recorder), referee::ShowBoard::Never).unwrap();
+
+    assert_eq!(folder.read_dir().unwrap().count(), result.rounds as usize + 1);
+    resimulate::<Map16x8>(&folder);
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn it_records_a_rollout_that_simulates_the_same_way() {
+    let folder = scratch_folder("rollout");
+    let state = referee::initial_state::<Map20x10>(GameConfig::default());
+    let mut rng = XorShiftRng::from_seed(INIT_SEED);
+    let mut recorder = ReplayRecorder::create(&folder).unwrap();
+    recorder::record_rollout(&state, &RandomMoveOptions::default(), &mut rng, &mut recorder).unwrap();
+
+    resimulate::<Map20x10>(&folder);
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+fn resimulate<M: MapSize>(replay_folder: &Path) {
+    let length = replay_folder.read_dir().unwrap().count()-1;
+    let mut state = json::read_state_from_file::<M>(&recorder::round_folder(replay_folder, 0).join("state.json").to_string_lossy()).unwrap();
+
+    for i in 0..length as u16 {
+        let round_folder = recorder::round_folder(replay_folder, i);
+        let player = command::read_command_from_file::<M>(&round_folder.join("PlayerCommand.txt").to_string_lossy()).unwrap();
+        let opponent = command::read_command_from_file::<M>(&round_folder.join("OpponentCommand.txt").to_string_lossy()).unwrap();
+        let expected_state = json::read_state_from_file::<M>(&recorder::round_folder(replay_folder, i+1).join("state.json").to_string_lossy()).unwrap();
+
+        state.simulate(player, opponent);
+        assert_eq!(state.sorted(), expected_state.sorted(), "\nFailed on state {}\n", i+1);
+    }
+}
+
+#[test]
+fn it_imports_a_server_replay_with_player_b_listed_first() {
+    let recorded = scratch_folder("import-recorded");
+    let server = scratch_folder("import-server");
+    let imported = scratch_folder("import-output");
+    let rounds = record_random_game(&recorded);
+    write_server_replay(&recorded, &server);
+
+    let report = import::import_replay(&server, &imported).unwrap();
+    assert_eq!(report.rounds, rounds);
+    assert_eq!(report.mismatched_rounds, Vec::<u16>::new());
+    for round in 0..rounds {
+        for file in &["state.json", "PlayerCommand.txt", "OpponentCommand.txt"] {
+            let expected = fs::read(recorder::round_folder(&recorded, round).join(file)).unwrap();
+            let actual = fs::read(recorder::round_folder(&imported, round).join(file)).unwrap();
+            assert_eq!(actual, expected, "{} in round {}", file, round);
+        }
+    }
+
+    for folder in &[recorded, server, imported] {
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
+
+#[test]
+fn it_reports_rounds_that_dont_simulate_the_same_way() {
+    let folder = scratch_folder("mismatch");
+    record_random_game(&folder);
+
+    let state_path = recorder::round_folder(&folder, 5).join("state.json");
+    let mut state = json::read_state_from_file::<Map16x8>(&state_path.to_string_lossy()).unwrap();
+    state.player.energy += 1;
+    json::write_state_to_file(&state, &state_path.to_string_lossy()).unwrap();
+
+    let report = import::validate_replay(&folder).unwrap();
+    assert_eq!(report.mismatched_rounds, vec!(4, 5));
+    fs::remove_dir_all(&folder).unwrap();
+}
+
+#[test]
+fn it_refuses_to_import_a_replay_with_missing_files() {
+    let recorded = scratch_folder("missing-recorded");
+    let server = scratch_folder("missing-server");
+    let imported = scratch_folder("missing-output");
+    record_random_game(&recorded);
+    write_server_replay(&recorded, &server);
+    fs::remove_file(server.join("Round 003").join("1 - second").join("PlayerCommand.txt")).unwrap();
+
+    assert!(import::import_replay(&server, &imported).is_err());
+
+    for folder in &[recorded, server, imported] {
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
+
+fn record_random_game(folder: &Path) -> u16 {
+    let state = referee::initial_state::<Map16x8>(GameConfig::default());
+    let random: Bot = "random".parse().unwrap();
+    let mut recorder = ReplayRecorder::create(folder).unwrap();
+    referee::play_match(state, &random, &random, Duration::milliseconds(0), INIT_SEED, Some(&mut recorder), referee::ShowBoard::Never).unwrap().rounds
+}
+
+/**
+ * Lays out a recorded game the way the game server does, with player
+ * B's folder sorted first. Player B is given the map with its own half
+ * on the left.
+ */
+fn write_server_replay(recorded: &Path, server: &Path) {
+    let rounds = recorded.read_dir().unwrap().count() as u16;
+    for round in 0..rounds {
+        let recorded_round = recorder::round_folder(recorded, round);
+        let server_round = recorder::round_folder(server, round);
+        let a_folder = server_round.join("2 - first");
+        let b_folder = server_round.join("1 - second");
+        fs::create_dir_all(&a_folder).unwrap();
+        fs::create_dir_all(&b_folder).unwrap();
+
+        fs::copy(recorded_round.join("state.json"), a_folder.join("JsonMap.json")).unwrap();
+        let mut state = json::read_state_from_file::<Map16x8>(&recorded_round.join("state.json").to_string_lossy()).unwrap();
+        state.swap();
+        let b_state_path = b_folder.join("JsonMap.json");
+        json::write_state_to_file(&state, &b_state_path.to_string_lossy()).unwrap();
+        let b_state = fs::read_to_string(&b_state_path).unwrap()
+            .replace("\"A\"", "\"a\"")
+            .replace("\"B\"", "\"A\"")
+            .replace("\"a\"", "\"B\"");
+        fs::write(&b_state_path, b_state).unwrap();
+
+        if round < rounds - 1 {
+            fs::copy(recorded_round.join("PlayerCommand.txt"), a_folder.join("PlayerCommand.txt")).unwrap();
+            fs::copy(recorded_round.join("OpponentCommand.txt"), b_folder.join("PlayerCommand.txt")).unwrap();
+        }
+    }
+}
+
+#[test]
+fn it_lists_the_fields_and_cells_that_differ() {
+    let start = referee::initial_state::<Map16x8>(GameConfig::default());
+    let mut predicted = start.clone();
+    predicted.simulate(Command::Build(Point::new(0, 1), BuildingType::Energy), Command::Nothing);
+    let mut actual = start.clone();
+    actual.simulate(Command::Build(Point::new(0, 2), BuildingType::Energy), Command::Nothing);
+    actual.opponent.energy += 1;
+
+    let differences = divergence::diff_states(&predicted, &actual);
+    assert_eq!(differences.len(), 3, "{:?}", differences);
+    assert_eq!(differences[0], Difference::Field { name: "B energy".to_string(), predicted: "25".to_string(), actual: "26".to_string() });
+    match (&differences[1], &differences[2]) {
+        (Difference::Cell { x: 0, y: 1, predicted: p1, actual: a1 }, Difference::Cell { x: 0, y: 2, predicted: p2, actual: a2 }) => {
+            assert!(p1.building.is_some() && a1.building.is_none());
+            assert!(p2.building.is_none() && a2.building.is_some());
+        },
+        _ => panic!("Expected two cells to differ, found {:?}", differences)
+    }
+
+    let boards = divergence::side_by_side_boards(&predicted, &actual);
+    assert_eq!(boards.lines().count(), 1 + 8);
+    assert!(boards.lines().nth(2).unwrap().ends_with(&format!("|  X {}", " . ".repeat(15))));
+}
+
+#[test]
+fn it_shows_each_round_of_a_replay_with_the_logged_scores() {
+    let folder = scratch_folder("viewer");
+    let rounds = record_random_game(&folder);
+    fs::write(recorder::round_folder(&folder, 3).join(recorder::DECISION_FILE),
+              r#"{"round":3,"choice":"1,2,2","candidates":[{"command":"No Command","winRatio":-120},{"command":"1,2,2","winRatio":4250}]}"#).unwrap();
+
+    let replay = Replay::<Map16x8>::load(&folder, &folder).unwrap();
+    assert_eq!(replay.last_round(), rounds);
+    assert_eq!(replay.commands.len(), rounds as usize);
+
+    let shown = replay.render_round(3, false);
+    assert!(shown.starts_with("Round 3 (Continue)"));
+    assert!(shown.contains("The engine predicts the next round exactly"));
+    assert!(shown.contains("The bot chose '1,2,2'. Best scoring moves:\n       1,2,2   42.50% from 0 games\n  No Command   -1.20% from 0 games\n"));
+    assert!(!replay.render_round(4, false).contains("Best scoring moves"));
+    assert!(replay.render_round(rounds, false).contains("Final state"));
+
+    fs::remove_dir_all(&folder).unwrap();
+}